@@ -0,0 +1,44 @@
+//! Dumps the structures of two versions of a hash map into a DOT file.
+//!
+//! ```sh
+//! cargo run --example dot -- map.dot
+//! dot -Tsvg map.dot > map.svg
+//! ```
+
+use imleak::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+
+const KEYS: &[&str] = &[
+    "apple",
+    "banana",
+    "cherry",
+    "date",
+    "elderberry",
+    "fig",
+    "grape",
+    "honeydew",
+    "kiwi",
+    "lemon",
+    "mango",
+    "nectarine",
+    "orange",
+    "papaya",
+    "quince",
+    "raspberry",
+];
+
+fn main() -> io::Result<()> {
+    let path = env::args().nth(1).unwrap_or_else(|| "map.dot".into());
+
+    let mut m: HashMap<_, _> = HashMap::new();
+
+    for (i, k) in KEYS.iter().enumerate() {
+        m = m.insert(*k, i);
+    }
+
+    let mm = m.insert("strawberry", KEYS.len());
+
+    fs::write(path, HashMap::to_dot_many(&[&m, &mm]))
+}
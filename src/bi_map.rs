@@ -0,0 +1,236 @@
+use crate::hash_map::{DefaultBuildHasher, HashMap, HashMapIterator};
+use crate::pointer::{ArcK, PointerKind};
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{BuildHasher, Hash};
+use core::iter::FromIterator;
+
+/// A persistent bidirectional map between left and right values.
+pub struct BiMap<L: Eq + Hash, R: Eq + Hash, S = DefaultBuildHasher, P: PointerKind = ArcK> {
+    left: HashMap<L, R, S, P>,
+    right: HashMap<R, L, S, P>,
+}
+
+impl<L: Clone + Eq + Hash, R: Clone + Eq + Hash, P: PointerKind + Default>
+    BiMap<L, R, DefaultBuildHasher, P>
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<
+        L: Clone + Eq + Hash,
+        R: Clone + Eq + Hash,
+        S: BuildHasher + Clone,
+        P: PointerKind + Default,
+    > BiMap<L, R, S, P>
+{
+    pub fn with_hasher(s: S) -> Self {
+        Self::with_hasher_in(s, Default::default())
+    }
+}
+
+impl<L: Clone + Eq + Hash, R: Clone + Eq + Hash, S: BuildHasher + Clone, P: PointerKind>
+    BiMap<L, R, S, P>
+{
+    /// Creates a map which allocates its nodes through a pointer kind.
+    pub fn with_hasher_in(s: S, p: P) -> Self {
+        Self {
+            left: HashMap::with_hasher_in(s.clone(), p.clone()),
+            right: HashMap::with_hasher_in(s, p),
+        }
+    }
+
+    /// Inserts a pair removing existing pairs which conflict with it.
+    pub fn insert(&self, l: L, r: R) -> Self {
+        let m = self.remove_by_left(&l).unwrap_or_else(|| self.clone());
+        let m = m.remove_by_right(&r).unwrap_or(m);
+
+        Self {
+            left: m.left.insert(l.clone(), r.clone()),
+            right: m.right.insert(r, l),
+        }
+    }
+
+    pub fn remove_by_left<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> Option<Self>
+    where
+        L: Borrow<Q>,
+    {
+        let r = self.left.get(l)?;
+
+        Some(Self {
+            left: self.left.remove(l)?,
+            right: self.right.remove::<R>(r)?,
+        })
+    }
+
+    pub fn remove_by_right<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> Option<Self>
+    where
+        R: Borrow<Q>,
+    {
+        let l = self.right.get(r)?;
+
+        Some(Self {
+            left: self.left.remove::<L>(l)?,
+            right: self.right.remove(r)?,
+        })
+    }
+}
+
+impl<L: Eq + Hash, R: Eq + Hash, S: BuildHasher, P: PointerKind> BiMap<L, R, S, P> {
+    pub fn get_by_left<Q: ?Sized + Eq + Hash>(&self, l: &Q) -> Option<&R>
+    where
+        L: Borrow<Q>,
+    {
+        self.left.get(l)
+    }
+
+    pub fn get_by_right<Q: ?Sized + Eq + Hash>(&self, r: &Q) -> Option<&L>
+    where
+        R: Borrow<Q>,
+    {
+        self.right.get(r)
+    }
+
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+}
+
+impl<L: Eq + Hash, R: Eq + Hash, S: Clone, P: PointerKind> Clone for BiMap<L, R, S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+impl<L: Debug + Eq + Hash, R: Debug + Eq + Hash, S, P: PointerKind> Debug for BiMap<L, R, S, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self).finish()
+    }
+}
+
+impl<L: Eq + Hash, R: Eq + Hash, S: BuildHasher, P: PointerKind> PartialEq for BiMap<L, R, S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.left == other.left
+    }
+}
+
+impl<L: Eq + Hash, R: Eq + Hash, S: BuildHasher, P: PointerKind> Eq for BiMap<L, R, S, P> {}
+
+impl<
+        L: Clone + Eq + Hash,
+        R: Clone + Eq + Hash,
+        S: BuildHasher + Clone + Default,
+        P: PointerKind + Default,
+    > Default for BiMap<L, R, S, P>
+{
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<
+        L: Clone + Eq + Hash,
+        R: Clone + Eq + Hash,
+        S: BuildHasher + Clone + Default,
+        P: PointerKind + Default,
+    > FromIterator<(L, R)> for BiMap<L, R, S, P>
+{
+    fn from_iter<I: IntoIterator<Item = (L, R)>>(i: I) -> Self {
+        let mut m = Self::default();
+
+        for (l, r) in i {
+            m = m.insert(l, r);
+        }
+
+        m
+    }
+}
+
+impl<'a, L: Eq + Hash, R: Eq + Hash, S, P: PointerKind> IntoIterator for &'a BiMap<L, R, S, P> {
+    type IntoIter = HashMapIterator<'a, L, R, P>;
+    type Item = (&'a L, &'a R);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.left.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BiMap;
+    use rand::random;
+    use std::collections::HashMap;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[test]
+    fn new() {
+        BiMap::<usize, usize>::new();
+    }
+
+    #[test]
+    fn insert() {
+        let m = BiMap::<_, _>::new();
+
+        assert_eq!(m.len(), 0);
+        assert_eq!(m.insert(0, 1).len(), 1);
+        assert_eq!(m.insert(0, 1).get_by_left(&0), Some(&1));
+        assert_eq!(m.insert(0, 1).get_by_right(&1), Some(&0));
+        assert_eq!(m.insert(0, 1).insert(1, 0).len(), 2);
+    }
+
+    #[test]
+    fn insert_conflicting_pairs() {
+        let m = BiMap::<_, _>::new().insert(0, 1).insert(2, 3);
+
+        assert_eq!(m.insert(0, 3), BiMap::new().insert(0, 3));
+        assert_eq!(m.insert(0, 4), BiMap::new().insert(0, 4).insert(2, 3));
+        assert_eq!(m.insert(4, 1), BiMap::new().insert(4, 1).insert(2, 3));
+        assert_eq!(m.insert(0, 1), m);
+    }
+
+    #[test]
+    fn remove() {
+        let m = BiMap::<_, _>::new().insert(0, 1);
+
+        assert_eq!(m.remove_by_left(&0), Some(BiMap::new()));
+        assert_eq!(m.remove_by_left(&1), None);
+        assert_eq!(m.remove_by_right(&1), Some(BiMap::new()));
+        assert_eq!(m.remove_by_right(&0), None);
+    }
+
+    #[test]
+    fn insert_remove_many() {
+        let mut m: BiMap<u8, u8> = BiMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let (l, r) = (random(), random());
+
+            m = match random::<u8>() % 3 {
+                0 => m.remove_by_left(&l).unwrap_or(m),
+                1 => m.remove_by_right(&r).unwrap_or(m),
+                _ => m.insert(l, r),
+            };
+
+            let ls = m.into_iter().collect::<HashMap<_, _>>();
+            let rs = m
+                .into_iter()
+                .map(|(l, r)| (r, l))
+                .collect::<HashMap<_, _>>();
+
+            assert_eq!(ls.len(), m.len());
+            assert_eq!(rs.len(), m.len());
+            assert_eq!(m.get_by_left(&l), ls.get(&l).copied());
+            assert_eq!(m.get_by_right(&r), rs.get(&r).copied());
+        }
+    }
+}
@@ -0,0 +1,552 @@
+use crate::hash_map::{Builder, DefaultBuildHasher, HashMap, HashMapIterator};
+use crate::pointer::{ArcK, PointerKind, RcK};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{BuildHasher, Hash};
+use core::iter::FromIterator;
+
+/// A persistent directed graph with labeled edges.
+///
+/// Both successors and predecessors of nodes are stored so that removal of
+/// nodes can find their incident edges.
+pub struct Graph<N: Eq + Hash, E: PartialEq, S = DefaultBuildHasher, P: PointerKind = ArcK> {
+    edges: usize,
+    successors: HashMap<N, HashMap<N, E, S, P>, S, P>,
+    predecessors: HashMap<N, HashMap<N, (), S, P>, S, P>,
+}
+
+impl<N: Clone + Eq + Hash, E: Clone + PartialEq, P: PointerKind + Default>
+    Graph<N, E, DefaultBuildHasher, P>
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<
+        N: Clone + Eq + Hash,
+        E: Clone + PartialEq,
+        S: BuildHasher + Clone,
+        P: PointerKind + Default,
+    > Graph<N, E, S, P>
+{
+    pub fn with_hasher(s: S) -> Self {
+        Self::with_hasher_in(s, Default::default())
+    }
+}
+
+impl<N: Clone + Eq + Hash, E: Clone + PartialEq, S: BuildHasher + Clone, P: PointerKind>
+    Graph<N, E, S, P>
+{
+    /// Creates a graph which allocates its nodes through a pointer kind.
+    pub fn with_hasher_in(s: S, p: P) -> Self {
+        Self {
+            edges: 0,
+            successors: HashMap::with_hasher_in(s.clone(), p.clone()),
+            predecessors: HashMap::with_hasher_in(s, p),
+        }
+    }
+
+    pub fn add_node(&self, n: N) -> Self {
+        if self.contains_node(&n) {
+            return self.clone();
+        }
+
+        let s = self.successors.hasher();
+        let p = self.successors.pointer_kind();
+
+        Self {
+            edges: self.edges,
+            successors: self
+                .successors
+                .insert(n.clone(), HashMap::with_hasher_in(s.clone(), p.clone())),
+            predecessors: self
+                .predecessors
+                .insert(n, HashMap::with_hasher_in(s.clone(), p.clone())),
+        }
+    }
+
+    /// Adds an edge and its nodes replacing an existing label of the edge.
+    pub fn add_edge(&self, a: N, b: N, e: E) -> Self {
+        let g = self.add_node(a.clone()).add_node(b.clone());
+        let mut new = false;
+
+        let successors = g.successors.alter(a.clone(), |ss| {
+            let ss = ss?;
+            new = ss.get(&b).is_none();
+            Some(ss.insert(b.clone(), e))
+        });
+        let predecessors = g.predecessors.alter(b, |ps| ps.map(|ps| ps.insert(a, ())));
+
+        Self {
+            edges: g.edges + usize::from(new),
+            successors,
+            predecessors,
+        }
+    }
+
+    pub fn remove_edge(&self, a: &N, b: &N) -> Option<Self> {
+        let ss = self.successors.get(a)?.remove(b)?;
+        let ps = self.predecessors.get(b)?.remove(a)?;
+
+        Some(Self {
+            edges: self.edges - 1,
+            successors: self.successors.insert(a.clone(), ss),
+            predecessors: self.predecessors.insert(b.clone(), ps),
+        })
+    }
+
+    /// Removes a node together with its incident edges.
+    pub fn remove_node(&self, n: &N) -> Option<Self> {
+        let ss = self.successors.get(n)?;
+        let ps = self.predecessors.get(n)?;
+        let mut successors = self.successors.remove(n)?;
+        let mut predecessors = self.predecessors.remove(n)?;
+
+        for (s, _) in ss {
+            predecessors = predecessors.alter(s.clone(), |qs| {
+                qs.map(|qs| qs.remove(n).unwrap_or_else(|| qs.clone()))
+            });
+        }
+
+        for (p, _) in ps {
+            successors = successors.alter(p.clone(), |qs| {
+                qs.map(|qs| qs.remove(n).unwrap_or_else(|| qs.clone()))
+            });
+        }
+
+        Some(Self {
+            // A self loop is counted in both successors and predecessors.
+            edges: self.edges + usize::from(ss.get(n).is_some()) - ss.len() - ps.len(),
+            successors,
+            predecessors,
+        })
+    }
+
+    /// Returns nodes in an order where every edge points forward, or `None`
+    /// if the graph has a cycle.
+    pub fn topological_sort(&self) -> Option<Vec<&N>> {
+        let mut ds = self.scratch();
+        let mut ns = vec![];
+        let mut sorted = Vec::with_capacity(self.len());
+
+        for (n, ps) in &self.predecessors {
+            if ps.is_empty() {
+                ns.push(n);
+            } else {
+                ds.insert(n, ps.len());
+            }
+        }
+
+        while let Some(n) = ns.pop() {
+            sorted.push(n);
+
+            for (m, _) in self.successors(n) {
+                let d = ds.get(&m).copied()? - 1;
+
+                if d == 0 {
+                    ns.push(m);
+                }
+
+                ds.insert(m, d);
+            }
+        }
+
+        if sorted.len() == self.len() {
+            Some(sorted)
+        } else {
+            None
+        }
+    }
+
+    /// Returns nodes reachable from a node including itself.
+    pub fn reachable(&self, n: &N) -> Vec<&N> {
+        let mut visited = self.scratch();
+        let mut ns: Vec<&N> = self
+            .successors
+            .get_key_value(n)
+            .map(|(n, _)| n)
+            .into_iter()
+            .collect();
+        let mut reached = vec![];
+
+        while let Some(n) = ns.pop() {
+            if visited.get(&n).is_some() {
+                continue;
+            }
+
+            visited.insert(n, ());
+            reached.push(n);
+            ns.extend(self.successors(n).map(|(m, _)| m));
+        }
+
+        reached
+    }
+
+    /// Returns true if there is a path from one node to another.
+    pub fn is_reachable(&self, a: &N, b: &N) -> bool {
+        self.reachable(a).contains(&b)
+    }
+
+    // Temporary maps are updated in place with reference counting so that
+    // their nodes are not copied on every update. They are allocated on the
+    // global heap rather than by the graph's allocator and freed on drop.
+    fn scratch<V: Clone + PartialEq>(&self) -> Builder<&N, V, S, RcK> {
+        Builder::new(self.successors.hasher().clone(), RcK)
+    }
+}
+
+impl<N: Eq + Hash, E: PartialEq, S: BuildHasher, P: PointerKind> Graph<N, E, S, P> {
+    pub fn contains_node(&self, n: &N) -> bool {
+        self.successors.get(n).is_some()
+    }
+
+    /// Returns a label of an edge.
+    pub fn edge(&self, a: &N, b: &N) -> Option<&E> {
+        self.successors.get(a)?.get(b)
+    }
+
+    pub fn nodes(&self) -> GraphNodes<'_, N, E, S, P> {
+        GraphNodes {
+            iterator: self.successors.into_iter(),
+        }
+    }
+
+    pub fn successors(&self, n: &N) -> GraphSuccessors<'_, N, E, P> {
+        GraphSuccessors {
+            iterator: self.successors.get(n).map(|ss| ss.into_iter()),
+        }
+    }
+
+    pub fn predecessors(&self, n: &N) -> GraphPredecessors<'_, N, P> {
+        GraphPredecessors {
+            iterator: self.predecessors.get(n).map(|ps| ps.into_iter()),
+        }
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.successors.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.successors.is_empty()
+    }
+}
+
+impl<N: Eq + Hash, E: PartialEq, S: BuildHasher + Clone, P: PointerKind> Clone
+    for Graph<N, E, S, P>
+{
+    fn clone(&self) -> Self {
+        Self {
+            edges: self.edges,
+            successors: self.successors.clone(),
+            predecessors: self.predecessors.clone(),
+        }
+    }
+}
+
+impl<N: Debug + Eq + Hash, E: Debug + PartialEq, S: BuildHasher, P: PointerKind> Debug
+    for Graph<N, E, S, P>
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.nodes()
+                    .map(|n| (n, DebugSuccessors(self.successors.get(n).unwrap()))),
+            )
+            .finish()
+    }
+}
+
+struct DebugSuccessors<'a, N: Eq + Hash, E: PartialEq, S, P: PointerKind>(&'a HashMap<N, E, S, P>);
+
+impl<'a, N: Debug + Eq + Hash, E: Debug + PartialEq, S, P: PointerKind> Debug
+    for DebugSuccessors<'a, N, E, S, P>
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.0).finish()
+    }
+}
+
+impl<N: Eq + Hash, E: PartialEq, S: BuildHasher, P: PointerKind> PartialEq for Graph<N, E, S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.edges == other.edges && self.successors == other.successors
+    }
+}
+
+impl<N: Eq + Hash, E: Eq, S: BuildHasher, P: PointerKind> Eq for Graph<N, E, S, P> {}
+
+impl<
+        N: Clone + Eq + Hash,
+        E: Clone + PartialEq,
+        S: BuildHasher + Clone + Default,
+        P: PointerKind + Default,
+    > Default for Graph<N, E, S, P>
+{
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<
+        N: Clone + Eq + Hash,
+        E: Clone + PartialEq,
+        S: BuildHasher + Clone + Default,
+        P: PointerKind + Default,
+    > FromIterator<(N, N, E)> for Graph<N, E, S, P>
+{
+    fn from_iter<I: IntoIterator<Item = (N, N, E)>>(i: I) -> Self {
+        let mut g = Self::default();
+
+        for (a, b, e) in i {
+            g = g.add_edge(a, b, e);
+        }
+
+        g
+    }
+}
+
+pub struct GraphNodes<'a, N: Eq + Hash, E: PartialEq, S, P: PointerKind = ArcK> {
+    iterator: HashMapIterator<'a, N, HashMap<N, E, S, P>, P>,
+}
+
+impl<'a, N: Eq + Hash, E: PartialEq, S: BuildHasher, P: PointerKind> Iterator
+    for GraphNodes<'a, N, E, S, P>
+{
+    type Item = &'a N;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.next().map(|(n, _)| n)
+    }
+}
+
+pub struct GraphSuccessors<'a, N: Eq + Hash, E: PartialEq, P: PointerKind = ArcK> {
+    iterator: Option<HashMapIterator<'a, N, E, P>>,
+}
+
+impl<'a, N: Eq + Hash, E: PartialEq, P: PointerKind> Iterator for GraphSuccessors<'a, N, E, P> {
+    type Item = (&'a N, &'a E);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.as_mut()?.next()
+    }
+}
+
+pub struct GraphPredecessors<'a, N: Eq + Hash, P: PointerKind = ArcK> {
+    iterator: Option<HashMapIterator<'a, N, (), P>>,
+}
+
+impl<'a, N: Eq + Hash, P: PointerKind> Iterator for GraphPredecessors<'a, N, P> {
+    type Item = &'a N;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.as_mut()?.next().map(|(n, _)| n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Graph;
+    use crate::hash_map::DefaultBuildHasher;
+    use crate::pointer::{AllocK, CountingAllocator};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use rand::random;
+    use std::collections::{BTreeSet, HashMap, HashSet};
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[test]
+    fn new() {
+        Graph::<usize, ()>::new();
+    }
+
+    #[test]
+    fn add_node() {
+        let g = Graph::<usize, ()>::new();
+
+        assert_eq!(g.len(), 0);
+        assert_eq!(g.add_node(0).len(), 1);
+        assert_eq!(g.add_node(0).add_node(0).len(), 1);
+        assert!(g.add_node(0).contains_node(&0));
+        assert!(!g.add_node(0).contains_node(&1));
+    }
+
+    #[test]
+    fn add_edge() {
+        let g = Graph::<_, _>::new().add_edge(0, 1, "foo");
+
+        assert_eq!(g.len(), 2);
+        assert_eq!(g.edge_count(), 1);
+        assert_eq!(g.edge(&0, &1), Some(&"foo"));
+        assert_eq!(g.edge(&1, &0), None);
+        assert_eq!(g.add_edge(0, 1, "bar").edge(&0, &1), Some(&"bar"));
+        assert_eq!(g.add_edge(0, 1, "bar").edge_count(), 1);
+        assert_eq!(g.add_edge(1, 1, "bar").edge_count(), 2);
+    }
+
+    #[test]
+    fn remove_edge() {
+        let g = Graph::<_, _>::new().add_edge(0, 1, ());
+
+        assert_eq!(
+            g.remove_edge(&0, &1),
+            Some(Graph::new().add_node(0).add_node(1))
+        );
+        assert_eq!(g.remove_edge(&1, &0), None);
+        assert_eq!(g.remove_edge(&0, &2), None);
+    }
+
+    #[test]
+    fn remove_node() {
+        let g = Graph::<_, _>::new()
+            .add_edge(0, 1, ())
+            .add_edge(1, 2, ())
+            .add_edge(2, 0, ())
+            .add_edge(1, 1, ());
+
+        assert_eq!(g.remove_node(&3), None);
+        assert_eq!(g.remove_node(&1), Some(Graph::new().add_edge(2, 0, ())));
+        assert_eq!(g.remove_node(&1).unwrap().edge_count(), 1);
+        assert_eq!(g.edge_count(), 4);
+    }
+
+    #[test]
+    fn successors_and_predecessors() {
+        let g = Graph::<_, _>::new()
+            .add_edge(0, 1, 'a')
+            .add_edge(0, 2, 'b')
+            .add_edge(2, 1, 'c');
+
+        assert_eq!(
+            g.successors(&0).collect::<BTreeSet<_>>(),
+            [(&1, &'a'), (&2, &'b')].into()
+        );
+        assert_eq!(g.predecessors(&1).collect::<BTreeSet<_>>(), [&0, &2].into());
+        assert_eq!(g.successors(&1).count(), 0);
+        assert_eq!(g.predecessors(&3).count(), 0);
+        assert_eq!(g.nodes().collect::<BTreeSet<_>>(), [&0, &1, &2].into());
+    }
+
+    #[test]
+    fn topological_sort() {
+        let g = Graph::<_, _>::new()
+            .add_edge(0, 1, ())
+            .add_edge(0, 2, ())
+            .add_edge(2, 1, ())
+            .add_node(3);
+        let ns = g.topological_sort().unwrap();
+
+        assert_eq!(ns.len(), 4);
+
+        for (i, n) in ns.iter().enumerate() {
+            for (m, _) in g.successors(n) {
+                assert!(ns[i + 1..].contains(&m));
+            }
+        }
+
+        assert_eq!(g.add_edge(1, 0, ()).topological_sort(), None);
+        assert_eq!(g.add_edge(3, 3, ()).topological_sort(), None);
+        assert_eq!(Graph::<usize, ()>::new().topological_sort(), Some(vec![]));
+    }
+
+    #[test]
+    fn reachable() {
+        let g = Graph::<_, _>::new()
+            .add_edge(0, 1, ())
+            .add_edge(1, 2, ())
+            .add_edge(2, 1, ())
+            .add_edge(3, 0, ());
+
+        assert_eq!(
+            g.reachable(&0).into_iter().collect::<BTreeSet<_>>(),
+            [&0, &1, &2].into()
+        );
+        assert_eq!(g.reachable(&4), Vec::<&usize>::new());
+        assert!(g.is_reachable(&3, &2));
+        assert!(!g.is_reachable(&2, &0));
+    }
+
+    #[test]
+    fn traverse_without_graph_allocator() {
+        let n = AtomicUsize::new(0);
+        let mut g =
+            Graph::with_hasher_in(DefaultBuildHasher::default(), AllocK(CountingAllocator(&n)));
+
+        for i in 0..NUM_ITERATIONS {
+            g = g.add_edge(i, i + 1, ());
+        }
+
+        n.store(0, Ordering::SeqCst);
+
+        assert_eq!(g.topological_sort().unwrap().len(), NUM_ITERATIONS + 1);
+        assert_eq!(g.reachable(&0).len(), NUM_ITERATIONS + 1);
+        assert_eq!(n.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn add_remove_many() {
+        let mut g: Graph<u8, ()> = Graph::new();
+        let mut h: HashMap<u8, HashSet<u8>> = HashMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let a = random::<u8>() % 32;
+            let b = random::<u8>() % 32;
+
+            match random::<u8>() % 4 {
+                0 => {
+                    g = g.remove_node(&a).unwrap_or(g);
+                    h.remove(&a);
+
+                    for ss in h.values_mut() {
+                        ss.remove(&a);
+                    }
+                }
+                1 => {
+                    g = g.remove_edge(&a, &b).unwrap_or(g);
+
+                    if let Some(ss) = h.get_mut(&a) {
+                        ss.remove(&b);
+                    }
+                }
+                _ => {
+                    g = g.add_edge(a, b, ());
+                    h.entry(a).or_default().insert(b);
+                    h.entry(b).or_default();
+                }
+            }
+
+            assert_eq!(g.len(), h.len());
+            assert_eq!(g.edge_count(), h.values().map(|ss| ss.len()).sum::<usize>());
+            assert_eq!(
+                g.predecessors(&b).copied().collect::<HashSet<_>>(),
+                h.iter()
+                    .filter(|(_, ss)| ss.contains(&b))
+                    .map(|(a, _)| *a)
+                    .collect()
+            );
+        }
+
+        for (a, ss) in &h {
+            assert_eq!(
+                g.successors(a).map(|(b, _)| *b).collect::<HashSet<_>>(),
+                *ss
+            );
+        }
+    }
+
+    #[test]
+    fn from_iterator() {
+        assert_eq!(
+            vec![(0, 1, ()), (1, 2, ()), (0, 1, ())]
+                .into_iter()
+                .collect::<Graph<_, _>>(),
+            Graph::new().add_edge(1, 2, ()).add_edge(0, 1, ())
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use crate::hash_map::{DefaultBuildHasher, HashMap, HashMapIterator};
+use crate::pointer::{ArcK, PointerKind};
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{BuildHasher, Hash};
+use core::iter::FromIterator;
+
+/// A persistent hash multiset which counts occurrences of elements.
+pub struct HashBag<T: Eq + Hash, S = DefaultBuildHasher, P: PointerKind = ArcK> {
+    len: usize,
+    map: HashMap<T, usize, S, P>,
+}
+
+impl<T: Clone + Eq + Hash, P: PointerKind + Default> HashBag<T, DefaultBuildHasher, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Clone + Eq + Hash, S: BuildHasher + Clone, P: PointerKind + Default> HashBag<T, S, P> {
+    pub fn with_hasher(s: S) -> Self {
+        Self::with_hasher_in(s, Default::default())
+    }
+}
+
+impl<T: Clone + Eq + Hash, S: BuildHasher + Clone, P: PointerKind> HashBag<T, S, P> {
+    /// Creates a bag which allocates its nodes through a pointer kind.
+    pub fn with_hasher_in(s: S, p: P) -> Self {
+        Self {
+            len: 0,
+            map: HashMap::with_hasher_in(s, p),
+        }
+    }
+
+    pub fn insert(&self, x: T) -> Self {
+        self.insert_many(x, 1)
+    }
+
+    /// Inserts `n` occurrences of an element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total number of occurrences overflows `usize`.
+    pub fn insert_many(&self, x: T, n: usize) -> Self {
+        if n == 0 {
+            return self.clone();
+        }
+
+        // Counts of elements never exceed the total number of occurrences.
+        Self {
+            len: self.len.checked_add(n).expect("too many occurrences"),
+            map: self.map.alter(x, |m| Some(m.copied().unwrap_or(0) + n)),
+        }
+    }
+
+    /// Removes one occurrence of an element.
+    pub fn remove_one<Q: ?Sized + Eq + Hash>(&self, x: &Q) -> Option<Self>
+    where
+        T: Borrow<Q>,
+    {
+        let (x, &n) = self.map.get_key_value(x)?;
+
+        Some(Self {
+            len: self.len - 1,
+            map: if n > 1 {
+                self.map.insert(x.clone(), n - 1)
+            } else {
+                self.map.remove::<T>(x)?
+            },
+        })
+    }
+
+    /// Returns a bag whose counts are sums of counts in both bags.
+    ///
+    /// Nodes of bags with the default hasher are merged one by one and
+    /// subtrees in only one of them are reused. Bags with other hashers are
+    /// merged element by element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total number of occurrences overflows `usize`.
+    pub fn union(&self, other: &Self) -> Self
+    where
+        S: 'static,
+    {
+        Self {
+            len: self
+                .len
+                .checked_add(other.len)
+                .expect("too many occurrences"),
+            map: self.map.merge(&other.map, true, |m, n| Some(m + n)),
+        }
+    }
+
+    /// Returns a bag whose counts are minimums of counts in both bags.
+    ///
+    /// Nodes of bags with the default hasher are merged one by one and
+    /// subtrees shared by both are reused. Bags with other hashers are merged
+    /// element by element.
+    pub fn intersection(&self, other: &Self) -> Self
+    where
+        S: 'static,
+    {
+        let mut len = 0;
+        let map = self.map.merge(&other.map, false, |m, n| {
+            let n = *m.min(n);
+            len += n;
+            Some(n)
+        });
+
+        Self { len, map }
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher, P: PointerKind> HashBag<T, S, P> {
+    /// Returns the number of occurrences of an element.
+    pub fn count<Q: ?Sized + Eq + Hash>(&self, x: &Q) -> usize
+    where
+        T: Borrow<Q>,
+    {
+        self.map.get(x).copied().unwrap_or(0)
+    }
+
+    /// Returns the total number of occurrences of all elements.
+    pub fn total_len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of distinct elements.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T: Eq + Hash, S: Clone, P: PointerKind> Clone for HashBag<T, S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            len: self.len,
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<T: Debug + Eq + Hash, S, P: PointerKind> Debug for HashBag<T, S, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self).finish()
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher, P: PointerKind> PartialEq for HashBag<T, S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.map == other.map
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher, P: PointerKind> Eq for HashBag<T, S, P> {}
+
+impl<T: Clone + Eq + Hash, S: BuildHasher + Clone + Default, P: PointerKind + Default> Default
+    for HashBag<T, S, P>
+{
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<T: Clone + Eq + Hash, S: BuildHasher + Clone + Default, P: PointerKind + Default>
+    FromIterator<T> for HashBag<T, S, P>
+{
+    fn from_iter<I: IntoIterator<Item = T>>(i: I) -> Self {
+        let mut b = Self::default();
+
+        for x in i {
+            b = b.insert(x);
+        }
+
+        b
+    }
+}
+
+impl<'a, T: Eq + Hash, S, P: PointerKind> IntoIterator for &'a HashBag<T, S, P> {
+    type IntoIter = HashMapIterator<'a, T, usize, P>;
+    type Item = (&'a T, &'a usize);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HashBag;
+    use core::hash::{Hash, Hasher};
+    use rand::random;
+    use std::collections::hash_map::RandomState;
+    use std::collections::HashMap;
+    use std::string::String;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct CollidingKey(u8);
+
+    impl Hash for CollidingKey {
+        fn hash<H: Hasher>(&self, h: &mut H) {
+            (self.0 % 4).hash(h)
+        }
+    }
+
+    #[test]
+    fn new() {
+        HashBag::<usize>::new();
+    }
+
+    #[test]
+    fn insert() {
+        let b = HashBag::<_>::new();
+
+        assert_eq!(b.total_len(), 0);
+        assert_eq!(b.insert(0).total_len(), 1);
+        assert_eq!(b.insert(0).insert(0).total_len(), 2);
+        assert_eq!(b.insert(0).insert(0).len(), 1);
+        assert_eq!(b.insert(0).insert(1).len(), 2);
+        assert_eq!(b.insert_many(0, 3).count(&0), 3);
+        assert_eq!(b.insert_many(0, 0), b);
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_many_overflow() {
+        HashBag::<_>::new().insert(0).insert_many(1, usize::MAX);
+    }
+
+    #[test]
+    fn remove_one() {
+        let b = HashBag::<_>::new();
+
+        assert_eq!(b.remove_one(&0), None);
+        assert_eq!(b.insert(0).remove_one(&0), Some(b.clone()));
+        assert_eq!(b.insert(0).insert(0).remove_one(&0), Some(b.insert(0)));
+        assert_eq!(b.insert(0).remove_one(&1), None);
+    }
+
+    #[test]
+    fn remove_one_borrowed() {
+        let b = HashBag::<String>::new().insert_many("foo".into(), 2);
+
+        assert_eq!(b.remove_one("foo").unwrap().count("foo"), 1);
+        assert_eq!(b.remove_one("bar"), None);
+    }
+
+    #[test]
+    fn union() {
+        let b = HashBag::<_>::new().insert_many(0, 2).insert(1);
+        let c = HashBag::new().insert(0).insert(2);
+
+        assert_eq!(
+            b.union(&c),
+            HashBag::new().insert_many(0, 3).insert(1).insert(2)
+        );
+        assert_eq!(b.union(&c), c.union(&b));
+        assert_eq!(b.union(&HashBag::new()), b);
+    }
+
+    #[test]
+    fn intersection() {
+        let b = HashBag::<_>::new().insert_many(0, 2).insert(1);
+        let c = HashBag::new().insert_many(0, 3).insert(2);
+
+        assert_eq!(b.intersection(&c), HashBag::new().insert_many(0, 2));
+        assert_eq!(b.intersection(&c), c.intersection(&b));
+        assert!(b.intersection(&HashBag::new()).is_empty());
+    }
+
+    #[test]
+    fn insert_remove_many() {
+        let mut b: HashBag<u8> = HashBag::new();
+        let mut m: HashMap<u8, usize> = HashMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let x = random::<u8>() % 32;
+
+            if random() {
+                let n = random::<usize>() % 4;
+                b = b.insert_many(x, n);
+                *m.entry(x).or_default() += n;
+            } else {
+                b = b.remove_one(&x).unwrap_or(b);
+
+                if let Some(n) = m.get_mut(&x) {
+                    *n -= 1;
+                }
+            }
+
+            m.retain(|_, n| *n > 0);
+
+            assert_eq!(b.count(&x), m.get(&x).copied().unwrap_or(0));
+            assert_eq!(b.len(), m.len());
+            assert_eq!(b.total_len(), m.values().sum::<usize>());
+        }
+
+        assert_eq!(
+            b.into_iter()
+                .map(|(x, n)| (*x, *n))
+                .collect::<HashMap<_, _>>(),
+            m
+        );
+    }
+
+    #[test]
+    fn union_intersection_many() {
+        for _ in 0..NUM_ITERATIONS / 64 {
+            let xs = (0..64).map(|_| random::<u8>() % 16);
+            let ys = (0..64).map(|_| random::<u8>() % 16);
+            let b: HashBag<u8> = xs.collect();
+            let c: HashBag<u8> = ys.collect();
+            let u = b.union(&c);
+            let i = b.intersection(&c);
+
+            for x in 0..16 {
+                assert_eq!(u.count(&x), b.count(&x) + c.count(&x));
+                assert_eq!(i.count(&x), b.count(&x).min(c.count(&x)));
+            }
+
+            assert_eq!(u.total_len(), b.total_len() + c.total_len());
+            assert_eq!(u.len(), u.into_iter().count());
+            assert_eq!(i.total_len(), i.into_iter().map(|(_, n)| n).sum::<usize>());
+            assert_eq!(i.len(), i.into_iter().count());
+            assert_eq!(u.map.check_invariants(), Ok(()));
+            assert_eq!(i.map.check_invariants(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn union_intersection_colliding() {
+        for _ in 0..NUM_ITERATIONS / 64 {
+            let b: HashBag<_> = (0..64).map(|_| CollidingKey(random::<u8>() % 32)).collect();
+            let c: HashBag<_> = (0..64).map(|_| CollidingKey(random::<u8>() % 32)).collect();
+            let u = b.union(&c);
+            let i = b.intersection(&c);
+
+            for x in (0..32).map(CollidingKey) {
+                assert_eq!(u.count(&x), b.count(&x) + c.count(&x));
+                assert_eq!(i.count(&x), b.count(&x).min(c.count(&x)));
+            }
+
+            assert_eq!(u.len(), u.into_iter().count());
+            assert_eq!(i.len(), i.into_iter().count());
+            assert_eq!(u.map.check_invariants(), Ok(()));
+            assert_eq!(i.map.check_invariants(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn union_intersection_with_different_hashers() {
+        let b: HashBag<_, _> = (0..64).fold(HashBag::with_hasher(RandomState::new()), |b, x| {
+            b.insert(x % 16)
+        });
+        let c = (0..64).fold(HashBag::with_hasher(RandomState::new()), |c, x| {
+            c.insert_many(x % 32, 2)
+        });
+        let u = b.union(&c);
+        let i = b.intersection(&c);
+
+        for x in 0..32 {
+            assert_eq!(u.count(&x), b.count(&x) + c.count(&x));
+            assert_eq!(i.count(&x), b.count(&x).min(c.count(&x)));
+        }
+
+        assert_eq!(u.map.check_invariants(), Ok(()));
+        assert_eq!(i.map.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn union_intersection_share_nodes() {
+        let b: HashBag<usize> = (0..NUM_ITERATIONS).collect();
+        let c = b.insert(0);
+        let d = HashBag::new().insert(NUM_ITERATIONS);
+        let bytes = b.map.shared_bytes_with(&b.map);
+
+        assert_eq!(b.intersection(&c), b);
+        assert_eq!(b.union(&d), b.insert(NUM_ITERATIONS));
+        assert!(b.intersection(&c).map.shared_bytes_with(&b.map) * 10 >= bytes * 9);
+        assert!(b.union(&d).map.shared_bytes_with(&b.map) * 10 >= bytes * 9);
+    }
+
+    #[test]
+    fn from_iterator() {
+        assert_eq!(
+            vec![0, 1, 0].into_iter().collect::<HashBag<_>>(),
+            HashBag::new().insert_many(0, 2).insert(1)
+        );
+    }
+}
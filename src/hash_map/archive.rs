@@ -0,0 +1,741 @@
+//! Archived hash maps which are looked up in place without deserialization.
+//!
+//! An archive is a byte buffer in the native byte order where child nodes are
+//! referred to by relative offsets and collision buckets are flattened into
+//! arrays. Buffers passed to [`ArchivedHashMap::new`] must be aligned to 8
+//! bytes, which holds for memory-mapped files.
+//!
+//! Keys are placed by their hashes, so archives can be accessed only with the
+//! hasher of their original maps. A fingerprint of the hasher is stored in
+//! archives and checked when they are accessed.
+
+use super::bucket::Bucket;
+use super::entry::Entry;
+use super::hamt::{HAMT, MAX_LEVEL};
+use super::hashed_key::HashedKey;
+use super::{DefaultBuildHasher, HashMap};
+use crate::pointer::PointerKind;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::convert::TryFrom;
+use core::fmt::{self, Display, Formatter};
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::str;
+
+const MAGIC: &[u8; 4] = b"IMLA";
+const TRAILER_SIZE: usize = 24;
+const FINGERPRINT_PROBE: &[u8] = b"imleak archive";
+const LEAF_ALIGNMENT: usize = 8;
+const LEAF_HEADER_SIZE: usize = 8;
+
+/// A type which can be archived and accessed in place.
+///
+/// # Safety
+///
+/// `access` must be sound for any bytes accepted by `check` at an address
+/// aligned to 8 bytes. Archived values must hash and compare in the same way
+/// as their original ones.
+pub unsafe trait Archive {
+    type Archived: ?Sized;
+
+    fn archive(&self, bs: &mut Vec<u8>);
+    fn check(bs: &[u8]) -> bool;
+
+    /// # Safety
+    ///
+    /// `bs` must be accepted by `check` and aligned to 8 bytes.
+    unsafe fn access(bs: &[u8]) -> &Self::Archived;
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArchiveError {
+    InvalidData,
+    HasherMismatch,
+    Misaligned,
+    TooLarge,
+}
+
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ArchiveError::InvalidData => write!(f, "invalid archive data"),
+            ArchiveError::HasherMismatch => write!(f, "mismatched archive hasher"),
+            ArchiveError::Misaligned => write!(f, "misaligned archive"),
+            ArchiveError::TooLarge => write!(f, "too large archive"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ArchiveError {}
+
+/// Archives a hash map into bytes.
+///
+/// Archives are limited to 4 GiB as offsets and sizes are 32 bits.
+pub fn to_bytes<K: Eq + Hash + Archive, V: PartialEq + Archive, S: BuildHasher, P: PointerKind>(
+    m: &HashMap<K, V, S, P>,
+) -> Result<Vec<u8>, ArchiveError> {
+    let mut bs = vec![];
+    let p = write_hamt(&mut bs, &m.hamt)?;
+
+    align(&mut bs, LEAF_ALIGNMENT);
+    write_u64(&mut bs, m.len as u64);
+    write_u64(&mut bs, fingerprint(&m.hasher));
+    write_offset(&mut bs, p)?;
+    bs.extend_from_slice(MAGIC);
+
+    Ok(bs)
+}
+
+/// A hash map accessed in place in an archive.
+pub struct ArchivedHashMap<'a, K: Archive, V: Archive, S = DefaultBuildHasher> {
+    bytes: &'a [u8],
+    len: usize,
+    root: usize,
+    hasher: S,
+    phantom: PhantomData<(K, V)>,
+}
+
+impl<'a, K: Archive, V: Archive> ArchivedHashMap<'a, K, V> {
+    pub fn new(bs: &'a [u8]) -> Result<Self, ArchiveError> {
+        Self::with_hasher(bs, Default::default())
+    }
+}
+
+impl<'a, K: Archive, V: Archive, S: BuildHasher> ArchivedHashMap<'a, K, V, S> {
+    /// Accesses an archive with a hasher builder.
+    ///
+    /// The hasher builder must hash keys in the same way as the one of the
+    /// original map. Otherwise, it returns [`ArchiveError::HasherMismatch`].
+    pub fn with_hasher(bs: &'a [u8], s: S) -> Result<Self, ArchiveError> {
+        if bs.as_ptr() as usize & (LEAF_ALIGNMENT - 1) != 0 {
+            return Err(ArchiveError::Misaligned);
+        } else if bs.len() < TRAILER_SIZE || &bs[bs.len() - 4..] != MAGIC {
+            return Err(ArchiveError::InvalidData);
+        }
+
+        let t = bs.len() - TRAILER_SIZE;
+        let len = read_u64(bs, t).ok_or(ArchiveError::InvalidData)?;
+        let root = read_offset(bs, t + 16).ok_or(ArchiveError::InvalidData)?;
+
+        if read_u64(bs, t + 8) != Some(fingerprint(&s)) {
+            return Err(ArchiveError::HasherMismatch);
+        }
+
+        let m = Self {
+            bytes: bs,
+            len: len as usize,
+            root,
+            hasher: s,
+            phantom: PhantomData,
+        };
+
+        if m.check_node(&mut BTreeMap::new(), root, NodeKind::HAMT(0)) == Some(m.len) {
+            Ok(m)
+        } else {
+            Err(ArchiveError::InvalidData)
+        }
+    }
+
+    pub fn get<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<&'a V::Archived>
+    where
+        K::Archived: Borrow<Q>,
+    {
+        let mut hk = HashedKey::new(k, &self.hasher);
+        let mut p = self.root;
+
+        loop {
+            let i = hk.entry_index();
+
+            match self.hamt_entry(p, i) {
+                ArchivedEntry::Empty => return None,
+                ArchivedEntry::KeyValue(p) => return self.get_key_value(p, hk.key()),
+                ArchivedEntry::HAMT(pp) => {
+                    p = pp;
+                    hk = hk.increment_level();
+                }
+                ArchivedEntry::Bucket(p) => {
+                    return (0..self.u32(p) as usize)
+                        .find_map(|i| self.get_key_value(self.offset(p + 4 + 4 * i), hk.key()))
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn get_key_value<Q: ?Sized + Eq>(&self, p: usize, k: &Q) -> Option<&'a V::Archived>
+    where
+        K::Archived: Borrow<Q>,
+    {
+        let (kk, p) = self.leaf(p);
+
+        if unsafe { K::access(kk) }.borrow() == k {
+            Some(unsafe { V::access(self.leaf(p).0) })
+        } else {
+            None
+        }
+    }
+
+    fn hamt_entry(&self, p: usize, i: usize) -> ArchivedEntry {
+        let bit = 1 << i;
+        let bs = [self.u32(p), self.u32(p + 4), self.u32(p + 8)];
+        let o = p + 12 + 4 * ((bs[0] | bs[1] | bs[2]) & (bit - 1)).count_ones() as usize;
+
+        if bs[0] & bit != 0 {
+            ArchivedEntry::KeyValue(self.offset(o))
+        } else if bs[1] & bit != 0 {
+            ArchivedEntry::HAMT(self.offset(o))
+        } else if bs[2] & bit != 0 {
+            ArchivedEntry::Bucket(self.offset(o))
+        } else {
+            ArchivedEntry::Empty
+        }
+    }
+
+    // Returns the data of a leaf and the position after it.
+    fn leaf(&self, p: usize) -> (&'a [u8], usize) {
+        let s = p + LEAF_HEADER_SIZE;
+        let e = s + self.u32(p) as usize;
+
+        (&self.bytes[s..e], aligned(e, LEAF_ALIGNMENT))
+    }
+
+    fn u32(&self, p: usize) -> u32 {
+        read_u32(self.bytes, p).expect("valid position")
+    }
+
+    fn offset(&self, p: usize) -> usize {
+        read_offset(self.bytes, p).expect("valid offset")
+    }
+
+    // Checks a node and returns a number of its key-value pairs. Nodes shared
+    // by many parents are checked only once.
+    fn check_node(
+        &self,
+        ns: &mut BTreeMap<(usize, NodeKind), usize>,
+        p: usize,
+        k: NodeKind,
+    ) -> Option<usize> {
+        if let Some(&n) = ns.get(&(p, k)) {
+            return Some(n);
+        }
+
+        let n = match k {
+            NodeKind::KeyValue => self.check_key_value(p)?,
+            NodeKind::HAMT(level) => self.check_hamt(ns, p, level)?,
+            NodeKind::Bucket => self.check_bucket(ns, p)?,
+        };
+
+        ns.insert((p, k), n);
+
+        Some(n)
+    }
+
+    fn check_hamt(
+        &self,
+        ns: &mut BTreeMap<(usize, NodeKind), usize>,
+        p: usize,
+        level: u8,
+    ) -> Option<usize> {
+        let bs = [
+            read_u32(self.bytes, p)?,
+            read_u32(self.bytes, p + 4)?,
+            read_u32(self.bytes, p + 8)?,
+        ];
+
+        if bs[0] & bs[1] != 0 || bs[1] & bs[2] != 0 || bs[2] & bs[0] != 0 {
+            return None;
+        }
+
+        let mut n = 0;
+
+        for i in 0..32 {
+            if (bs[0] | bs[1] | bs[2]) & (1 << i) == 0 {
+                continue;
+            }
+
+            let o = p + 12 + 4 * ((bs[0] | bs[1] | bs[2]) & ((1 << i) - 1)).count_ones() as usize;
+            let pp = read_offset(self.bytes, o).filter(|&pp| pp < p)?;
+
+            let k = match self.hamt_entry(p, i) {
+                ArchivedEntry::KeyValue(_) => NodeKind::KeyValue,
+                ArchivedEntry::HAMT(_) if level < MAX_LEVEL => NodeKind::HAMT(level + 1),
+                ArchivedEntry::Bucket(_) if level == MAX_LEVEL => NodeKind::Bucket,
+                _ => return None,
+            };
+
+            n = self.check_node(ns, pp, k)?.checked_add(n)?;
+        }
+
+        Some(n)
+    }
+
+    fn check_bucket(&self, ns: &mut BTreeMap<(usize, NodeKind), usize>, p: usize) -> Option<usize> {
+        let n = read_u32(self.bytes, p)? as usize;
+
+        for i in 0..n {
+            let pp = read_offset(self.bytes, p + 4 + 4 * i).filter(|&pp| pp < p)?;
+            self.check_node(ns, pp, NodeKind::KeyValue)?;
+        }
+
+        Some(n)
+    }
+
+    fn check_key_value(&self, p: usize) -> Option<usize> {
+        let p = self.check_leaf::<K>(p)?;
+        self.check_leaf::<V>(p)?;
+        Some(1)
+    }
+
+    fn check_leaf<T: Archive>(&self, p: usize) -> Option<usize> {
+        if p & (LEAF_ALIGNMENT - 1) != 0 {
+            return None;
+        }
+
+        let s = p + LEAF_HEADER_SIZE;
+        let e = s.checked_add(read_u32(self.bytes, p)? as usize)?;
+
+        if T::check(self.bytes.get(s..e)?) {
+            Some(aligned(e, LEAF_ALIGNMENT))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+enum NodeKind {
+    KeyValue,
+    HAMT(u8),
+    Bucket,
+}
+
+enum ArchivedEntry {
+    Empty,
+    KeyValue(usize),
+    HAMT(usize),
+    Bucket(usize),
+}
+
+fn write_hamt<K: Eq + Hash + Archive, V: PartialEq + Archive, P: PointerKind>(
+    bs: &mut Vec<u8>,
+    h: &HAMT<K, V, P>,
+) -> Result<usize, ArchiveError> {
+    let mut ms = [0u32; 3];
+    let mut ps = vec![];
+
+    for (i, e) in h.entries().iter().enumerate() {
+        let (j, p) = match e {
+            Entry::Empty => continue,
+            Entry::KeyValue(k, v) => (0, write_key_value(bs, k, v)?),
+            Entry::HAMT(h) => (1, write_hamt(bs, h)?),
+            Entry::Bucket(b) => (2, write_bucket(bs, b)?),
+        };
+
+        ms[j] |= 1 << i;
+        ps.push(p);
+    }
+
+    align(bs, 4);
+    let p = bs.len();
+
+    for m in &ms {
+        write_u32(bs, *m);
+    }
+
+    for p in ps {
+        write_offset(bs, p)?;
+    }
+
+    Ok(p)
+}
+
+fn write_bucket<K: Eq + Hash + Archive, V: PartialEq + Archive, P: PointerKind>(
+    bs: &mut Vec<u8>,
+    b: &Bucket<K, V, P>,
+) -> Result<usize, ArchiveError> {
+    let ps = b
+        .into_iter()
+        .map(|(k, v)| write_key_value(bs, k, v))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    align(bs, 4);
+    let p = bs.len();
+
+    write_u32(bs, ps.len() as u32);
+
+    for p in ps {
+        write_offset(bs, p)?;
+    }
+
+    Ok(p)
+}
+
+fn write_key_value<K: Archive, V: Archive>(
+    bs: &mut Vec<u8>,
+    k: &K,
+    v: &V,
+) -> Result<usize, ArchiveError> {
+    let p = write_leaf(bs, k)?;
+    write_leaf(bs, v)?;
+    Ok(p)
+}
+
+fn write_leaf<T: Archive>(bs: &mut Vec<u8>, x: &T) -> Result<usize, ArchiveError> {
+    align(bs, LEAF_ALIGNMENT);
+    let p = bs.len();
+
+    bs.extend_from_slice(&[0; LEAF_HEADER_SIZE]);
+    x.archive(bs);
+
+    let n = to_u32(bs.len() - p - LEAF_HEADER_SIZE)?;
+    bs[p..p + 4].copy_from_slice(&n.to_ne_bytes());
+
+    Ok(p)
+}
+
+// Offsets are relative to their own positions and point backward.
+// Hashers are identified by hashes of a fixed value.
+fn fingerprint<S: BuildHasher>(s: &S) -> u64 {
+    s.hash_one(FINGERPRINT_PROBE)
+}
+
+fn write_offset(bs: &mut Vec<u8>, p: usize) -> Result<(), ArchiveError> {
+    let o = to_u32(bs.len() - p)?;
+    write_u32(bs, o);
+    Ok(())
+}
+
+fn to_u32(n: usize) -> Result<u32, ArchiveError> {
+    u32::try_from(n).map_err(|_| ArchiveError::TooLarge)
+}
+
+fn read_offset(bs: &[u8], p: usize) -> Option<usize> {
+    p.checked_sub(read_u32(bs, p)? as usize)
+}
+
+fn write_u32(bs: &mut Vec<u8>, n: u32) {
+    bs.extend_from_slice(&n.to_ne_bytes());
+}
+
+fn read_u32(bs: &[u8], p: usize) -> Option<u32> {
+    let mut b = [0; 4];
+    b.copy_from_slice(bs.get(p..p.checked_add(4)?)?);
+    Some(u32::from_ne_bytes(b))
+}
+
+fn write_u64(bs: &mut Vec<u8>, n: u64) {
+    bs.extend_from_slice(&n.to_ne_bytes());
+}
+
+fn read_u64(bs: &[u8], p: usize) -> Option<u64> {
+    let mut b = [0; 8];
+    b.copy_from_slice(bs.get(p..p.checked_add(8)?)?);
+    Some(u64::from_ne_bytes(b))
+}
+
+fn align(bs: &mut Vec<u8>, n: usize) {
+    bs.resize(aligned(bs.len(), n), 0);
+}
+
+fn aligned(p: usize, n: usize) -> usize {
+    (p + n - 1) & !(n - 1)
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            unsafe impl Archive for $t {
+                type Archived = $t;
+
+                fn archive(&self, bs: &mut Vec<u8>) {
+                    bs.extend_from_slice(&self.to_ne_bytes());
+                }
+
+                fn check(bs: &[u8]) -> bool {
+                    bs.len() == size_of::<$t>() && align_of::<$t>() <= LEAF_ALIGNMENT
+                }
+
+                unsafe fn access(bs: &[u8]) -> &Self::Archived {
+                    &*(bs.as_ptr() as *const $t)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+unsafe impl Archive for bool {
+    type Archived = bool;
+
+    fn archive(&self, bs: &mut Vec<u8>) {
+        bs.push(*self as u8);
+    }
+
+    fn check(bs: &[u8]) -> bool {
+        bs == [0] || bs == [1]
+    }
+
+    unsafe fn access(bs: &[u8]) -> &Self::Archived {
+        &*(bs.as_ptr() as *const bool)
+    }
+}
+
+unsafe impl Archive for String {
+    type Archived = str;
+
+    fn archive(&self, bs: &mut Vec<u8>) {
+        bs.extend_from_slice(self.as_bytes());
+    }
+
+    fn check(bs: &[u8]) -> bool {
+        str::from_utf8(bs).is_ok()
+    }
+
+    unsafe fn access(bs: &[u8]) -> &Self::Archived {
+        str::from_utf8_unchecked(bs)
+    }
+}
+
+unsafe impl Archive for Vec<u8> {
+    type Archived = [u8];
+
+    fn archive(&self, bs: &mut Vec<u8>) {
+        bs.extend_from_slice(self);
+    }
+
+    fn check(_: &[u8]) -> bool {
+        true
+    }
+
+    unsafe fn access(bs: &[u8]) -> &Self::Archived {
+        bs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{DefaultBuildHasher, HashMap};
+    use super::{
+        align, fingerprint, to_bytes, write_leaf, write_offset, write_u32, write_u64, Archive,
+        ArchiveError, ArchivedHashMap, MAGIC,
+    };
+    use crate::pointer::{LeakK, RcK};
+    use rand::random;
+    use std::collections::hash_map::RandomState;
+    use std::hash::{Hash, Hasher};
+    use std::string::{String, ToString};
+    use std::vec;
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct CollidingKey(u8);
+
+    impl Hash for CollidingKey {
+        fn hash<H: Hasher>(&self, h: &mut H) {
+            0.hash(h)
+        }
+    }
+
+    unsafe impl Archive for CollidingKey {
+        type Archived = CollidingKey;
+
+        fn archive(&self, bs: &mut Vec<u8>) {
+            bs.push(self.0);
+        }
+
+        fn check(bs: &[u8]) -> bool {
+            bs.len() == 1
+        }
+
+        unsafe fn access(bs: &[u8]) -> &Self::Archived {
+            &*(bs.as_ptr() as *const CollidingKey)
+        }
+    }
+
+    struct AlignedBytes(Vec<u64>, usize);
+
+    impl AlignedBytes {
+        fn new(bs: &[u8]) -> Self {
+            let mut v = vec![0u64; bs.len().div_ceil(8)];
+
+            unsafe {
+                std::ptr::copy_nonoverlapping(bs.as_ptr(), v.as_mut_ptr() as *mut u8, bs.len());
+            }
+
+            Self(v, bs.len())
+        }
+
+        fn as_slice(&self) -> &[u8] {
+            unsafe { std::slice::from_raw_parts(self.0.as_ptr() as *const u8, self.1) }
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let bs = AlignedBytes::new(&to_bytes(&HashMap::<usize, usize>::new()).unwrap());
+        let m = ArchivedHashMap::<usize, usize>::new(bs.as_slice()).unwrap();
+
+        assert_eq!(m.len(), 0);
+        assert_eq!(m.get(&0), None);
+    }
+
+    #[test]
+    fn get() {
+        let mut h: HashMap<u64, i16> = HashMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            h = h.insert(random(), random());
+        }
+
+        let bs = AlignedBytes::new(&to_bytes(&h).unwrap());
+        let m = ArchivedHashMap::<u64, i16>::new(bs.as_slice()).unwrap();
+
+        assert_eq!(m.len(), h.len());
+
+        for (k, v) in &h {
+            assert_eq!(m.get(k), Some(v));
+        }
+
+        for _ in 0..NUM_ITERATIONS {
+            let k = random();
+            assert_eq!(m.get(&k), h.get(&k));
+        }
+    }
+
+    #[test]
+    fn get_strings() {
+        let h = HashMap::<_, _>::new()
+            .insert("foo".to_string(), vec![1, 2, 3])
+            .insert("bar".to_string(), vec![]);
+
+        let bs = AlignedBytes::new(&to_bytes(&h).unwrap());
+        let m = ArchivedHashMap::<String, Vec<u8>>::new(bs.as_slice()).unwrap();
+
+        assert_eq!(m.get("foo"), Some(&[1u8, 2, 3][..]));
+        assert_eq!(m.get("bar"), Some(&[][..]));
+        assert_eq!(m.get("baz"), None);
+    }
+
+    #[test]
+    fn get_with_buckets() {
+        let mut h = HashMap::<_, _>::new();
+
+        for k in 0..42 {
+            h = h.insert(CollidingKey(k), k as u32);
+        }
+
+        let bs = AlignedBytes::new(&to_bytes(&h).unwrap());
+        let m = ArchivedHashMap::<CollidingKey, u32>::new(bs.as_slice()).unwrap();
+
+        for k in 0..42 {
+            assert_eq!(m.get(&CollidingKey(k)), Some(&(k as u32)));
+        }
+
+        assert_eq!(m.get(&CollidingKey(42)), None);
+    }
+
+    #[test]
+    fn get_from_other_pointer_kinds() {
+        let h: HashMap<u8, u8, DefaultBuildHasher, RcK> = (0..64).map(|k| (k, k)).collect();
+        let bs = AlignedBytes::new(&to_bytes(&h).unwrap());
+
+        assert_eq!(
+            ArchivedHashMap::<u8, u8>::new(bs.as_slice())
+                .unwrap()
+                .get(&42),
+            Some(&42)
+        );
+
+        let h: HashMap<u8, u8, DefaultBuildHasher, LeakK> = (0..64).map(|k| (k, k)).collect();
+        let bs = AlignedBytes::new(&to_bytes(&h).unwrap());
+
+        assert_eq!(
+            ArchivedHashMap::<u8, u8>::new(bs.as_slice())
+                .unwrap()
+                .get(&42),
+            Some(&42)
+        );
+    }
+
+    #[test]
+    fn reject_other_hasher() {
+        let h = HashMap::<_, _, _>::with_hasher(RandomState::new()).insert(0usize, 0usize);
+        let bs = AlignedBytes::new(&to_bytes(&h).unwrap());
+
+        assert_eq!(
+            ArchivedHashMap::<usize, usize>::new(bs.as_slice()).err(),
+            Some(ArchiveError::HasherMismatch)
+        );
+        assert!(
+            ArchivedHashMap::<usize, usize, _>::with_hasher(bs.as_slice(), h.hasher().clone())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn reject_invalid_data() {
+        let bs =
+            AlignedBytes::new(&to_bytes(&HashMap::<_, _>::new().insert(0usize, 0usize)).unwrap());
+
+        assert_eq!(
+            ArchivedHashMap::<usize, usize>::new(&bs.as_slice()[1..]).err(),
+            Some(ArchiveError::Misaligned)
+        );
+        assert_eq!(
+            ArchivedHashMap::<usize, usize>::new(AlignedBytes::new(&[]).as_slice()).err(),
+            Some(ArchiveError::InvalidData)
+        );
+        assert_eq!(
+            ArchivedHashMap::<usize, u32>::new(bs.as_slice()).err(),
+            Some(ArchiveError::InvalidData)
+        );
+    }
+
+    #[test]
+    fn reject_shared_nodes() {
+        let mut bs = vec![];
+        let mut p = write_leaf(&mut bs, &0usize).unwrap();
+        write_leaf(&mut bs, &0usize).unwrap();
+
+        // Every node refers to its child from all of its entries.
+        for i in 0..13 {
+            let pp = bs.len();
+
+            write_u32(&mut bs, if i == 0 { !0 } else { 0 });
+            write_u32(&mut bs, if i == 0 { 0 } else { !0 });
+            write_u32(&mut bs, 0);
+
+            for _ in 0..32 {
+                write_offset(&mut bs, p).unwrap();
+            }
+
+            p = pp;
+        }
+
+        align(&mut bs, 8);
+        write_u64(&mut bs, 0);
+        write_u64(&mut bs, fingerprint(&DefaultBuildHasher::default()));
+        write_offset(&mut bs, p).unwrap();
+        bs.extend_from_slice(MAGIC);
+
+        assert_eq!(
+            ArchivedHashMap::<usize, usize>::new(AlignedBytes::new(&bs).as_slice()).err(),
+            Some(ArchiveError::InvalidData)
+        );
+    }
+}
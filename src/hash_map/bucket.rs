@@ -1,99 +1,251 @@
 use super::node::Node;
-use std::borrow::Borrow;
-use std::collections::hash_map::Iter;
-use std::collections::HashMap;
-use std::hash::Hash;
-
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Bucket<K: Eq + Hash, V: PartialEq> {
-    hash_map: HashMap<K, V>,
+use super::visitor::NodeVisitor;
+use crate::pointer::PointerKind;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::Hash;
+use core::mem;
+use core::slice::Iter;
+
+pub struct Bucket<K: Eq + Hash, V, P: PointerKind> {
+    key_values: P::Array<(K, V)>,
 }
 
-impl<K: Eq + Hash, V: PartialEq> Bucket<K, V> {
-    pub fn new(k: K, v: V) -> Self {
+impl<K: Eq + Hash, V: PartialEq, P: PointerKind> Bucket<K, V, P> {
+    pub fn new(k: K, v: V, p: &P) -> Self {
         Self {
-            hash_map: vec![(k, v)].into_iter().collect(),
+            key_values: p.array(vec![(k, v)]),
         }
     }
-}
 
-impl<K: Clone + Eq + Hash, V: Clone + PartialEq> Bucket<K, V> {
-    pub fn insert(&self, k: K, v: V) -> (Self, bool) {
-        let mut h = self.hash_map.clone();
+    pub fn from_key_values(kvs: Vec<(K, V)>, p: &P) -> Self {
+        Self {
+            key_values: p.array(kvs),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.key_values.len()
+    }
+
+    pub fn get<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.get_key_value(k).map(|(_, v)| v)
+    }
+
+    pub fn get_key_value<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        self.key_values
+            .iter()
+            .find(|(kk, _)| kk.borrow() == k)
+            .map(|(k, v)| (k, v))
+    }
+
+    pub fn visit_nodes<N: NodeVisitor<K, V>>(&self, n: &mut N) {
+        if !n.visit_bucket(self as *const Self as *const u8, mem::size_of::<Self>()) {
+            return;
+        }
+
+        if !self.key_values.is_empty() {
+            n.visit_buffer(
+                self.key_values.as_ptr() as *const u8,
+                self.key_values.len() * mem::size_of::<(K, V)>(),
+            );
+        }
 
-        match h.insert(k, v) {
-            Some(_) => (Self { hash_map: h }, false),
-            None => (Self { hash_map: h }, true),
+        for (k, v) in self.key_values.iter() {
+            n.visit_key_value(k, v);
         }
     }
 
-    pub fn remove<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<Self>
+    fn position<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<usize>
     where
         K: Borrow<Q>,
     {
-        let mut h = self.hash_map.clone();
-        h.remove(k).map(|_| Self { hash_map: h })
+        self.key_values.iter().position(|(kk, _)| kk.borrow() == k)
     }
+}
 
-    pub fn get<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<&V>
+// Key-value pairs are copied on every update as buckets are small.
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, P: PointerKind> Bucket<K, V, P> {
+    pub fn insert(&self, k: K, v: V, p: &P) -> (Self, bool) {
+        let mut b = self.clone();
+        let new = b.insert_mut(k, v, p);
+        (b, new)
+    }
+
+    pub fn insert_mut(&mut self, k: K, v: V, p: &P) -> bool {
+        let mut kvs = self.key_values.to_vec();
+
+        let new = match kvs.iter_mut().find(|(kk, _)| kk == &k) {
+            Some(kv) => {
+                *kv = (k, v);
+                false
+            }
+            None => {
+                kvs.push((k, v));
+                true
+            }
+        };
+
+        self.key_values = p.array(kvs);
+        new
+    }
+
+    pub fn remove<Q: ?Sized + Eq + Hash>(&self, k: &Q, p: &P) -> Option<Self>
     where
         K: Borrow<Q>,
     {
-        self.hash_map.get(k)
+        let i = self.position(k)?;
+        let mut kvs = self.key_values.to_vec();
+        kvs.swap_remove(i);
+
+        Some(Self {
+            key_values: p.array(kvs),
+        })
     }
 
-    #[cfg(test)]
-    pub fn len(&self) -> usize {
-        self.hash_map.len()
+    /// Merges key-value pairs of two buckets, combining values of keys in both
+    /// by a function.
+    pub fn merge(
+        &self,
+        other: &Self,
+        union: bool,
+        f: &mut impl FnMut(&V, &V) -> Option<V>,
+        p: &P,
+    ) -> Self {
+        let mut kvs = vec![];
+
+        for (k, v) in self.key_values.iter() {
+            match other.get(k) {
+                Some(w) => kvs.extend(f(v, w).map(|x| (k.clone(), x))),
+                None if union => kvs.push((k.clone(), v.clone())),
+                None => {}
+            }
+        }
+
+        if union {
+            kvs.extend(
+                other
+                    .key_values
+                    .iter()
+                    .filter(|(k, _)| self.get(k).is_none())
+                    .cloned(),
+            );
+        }
+
+        Self::from_key_values(kvs, p)
+    }
+
+    pub fn alter(&self, k: K, f: impl FnOnce(Option<&V>) -> Option<V>, p: &P) -> (Self, isize) {
+        let mut kvs = self.key_values.to_vec();
+
+        let d = match self.position(&k) {
+            Some(i) => match f(Some(&self.key_values[i].1)) {
+                None => {
+                    kvs.swap_remove(i);
+                    -1
+                }
+                Some(v) => {
+                    kvs[i] = (k, v);
+                    0
+                }
+            },
+            None => match f(None) {
+                None => return (self.clone(), 0),
+                Some(v) => {
+                    kvs.push((k, v));
+                    1
+                }
+            },
+        };
+
+        (
+            Self {
+                key_values: p.array(kvs),
+            },
+            d,
+        )
     }
 }
 
-impl<K: Eq + Hash, V: PartialEq> Node for Bucket<K, V> {
+impl<K: Eq + Hash, V: PartialEq, P: PointerKind> Clone for Bucket<K, V, P> {
+    fn clone(&self) -> Self {
+        Self {
+            key_values: self.key_values.clone(),
+        }
+    }
+}
+
+impl<K: Debug + Eq + Hash, V: Debug, P: PointerKind> Debug for Bucket<K, V, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Bucket")
+            .field("key_values", &&*self.key_values)
+            .finish()
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, P: PointerKind> PartialEq for Bucket<K, V, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.into_iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: Eq + Hash, V: Eq, P: PointerKind> Eq for Bucket<K, V, P> {}
+
+impl<K: Eq + Hash, V: PartialEq, P: PointerKind> Node for Bucket<K, V, P> {
     fn is_singleton(&self) -> bool {
-        self.hash_map.len() == 1
+        self.key_values.len() == 1
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct BucketIterator<'a, K, V> {
-    iterator: Iter<'a, K, V>,
+    iterator: Iter<'a, (K, V)>,
 }
 
 impl<'a, K, V> Iterator for BucketIterator<'a, K, V> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.iterator.next()
+        self.iterator.next().map(|(k, v)| (k, v))
     }
 }
 
-impl<'a, K: Eq + Hash, V: PartialEq> IntoIterator for &'a Bucket<K, V> {
+impl<'a, K: Eq + Hash, V: PartialEq, P: PointerKind> IntoIterator for &'a Bucket<K, V, P> {
     type IntoIter = BucketIterator<'a, K, V>;
     type Item = (&'a K, &'a V);
 
     fn into_iter(self) -> Self::IntoIter {
         BucketIterator {
-            iterator: self.hash_map.iter(),
+            iterator: self.key_values.iter(),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::*;
+    use crate::pointer::ArcK;
+
+    type Bucket<K, V> = super::Bucket<K, V, ArcK>;
 
     #[test]
     fn new() {
-        Bucket::new(42, 0);
+        Bucket::new(42, 0, &ArcK);
     }
 
     #[test]
     fn insert() {
-        let b = Bucket::new(42, 0);
+        let b = Bucket::new(42, 0, &ArcK);
 
         assert_eq!(b.len(), 1);
 
-        let (bb, new) = b.insert(0, 0);
+        let (bb, new) = b.insert(0, 0, &ArcK);
 
         assert!(new);
         assert_eq!(b.len(), 1);
@@ -102,15 +254,31 @@ mod test {
 
     #[test]
     fn remove() {
-        let b = Bucket::new(42, 0);
+        let b = Bucket::new(42, 0, &ArcK);
+
+        assert_eq!(b.remove(&42, &ArcK).unwrap().len(), 0);
+        assert_eq!(
+            b.insert(0, 0, &ArcK).0.remove(&42, &ArcK),
+            Some(Bucket::new(0, 0, &ArcK))
+        );
+    }
+
+    #[test]
+    fn alter() {
+        let b = Bucket::new(42, 0, &ArcK);
 
-        assert_eq!(b.remove(&42).unwrap().len(), 0);
-        assert_eq!(b.insert(0, 0).0.remove(&42), Some(Bucket::new(0, 0)));
+        assert_eq!(
+            b.alter(42, |_| Some(1), &ArcK),
+            (Bucket::new(42, 1, &ArcK), 0)
+        );
+        assert_eq!(b.alter(42, |_| None, &ArcK).0.len(), 0);
+        assert_eq!(b.alter(0, |_| Some(1), &ArcK), (b.insert(0, 1, &ArcK).0, 1));
+        assert_eq!(b.alter(0, |_| None, &ArcK), (b.clone(), 0));
     }
 
     #[test]
     fn get() {
-        let b = Bucket::new(42, 0);
+        let b = Bucket::new(42, 0, &ArcK);
 
         assert_eq!(b.get(&42), Some(&0));
         assert_eq!(b.get(&0), None);
@@ -118,7 +286,13 @@ mod test {
 
     #[test]
     fn eq() {
-        assert!(Bucket::new(0, 0).insert(1, 0) == Bucket::new(0, 0).insert(1, 0));
-        assert!(Bucket::new(0, 0).insert(1, 0) == Bucket::new(1, 0).insert(0, 0));
+        assert!(
+            Bucket::new(0, 0, &ArcK).insert(1, 0, &ArcK)
+                == Bucket::new(0, 0, &ArcK).insert(1, 0, &ArcK)
+        );
+        assert!(
+            Bucket::new(0, 0, &ArcK).insert(1, 0, &ArcK)
+                == Bucket::new(1, 0, &ArcK).insert(0, 0, &ArcK)
+        );
     }
 }
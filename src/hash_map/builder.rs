@@ -0,0 +1,77 @@
+use super::hamt::HAMT;
+use super::hashed_key::HashedKey;
+use super::HashMap;
+use crate::pointer::PointerKind;
+use core::hash::{BuildHasher, Hash};
+
+// A transient map which updates its nodes in place while they are not shared.
+pub struct Builder<K: Eq + Hash, V: PartialEq, S, P: PointerKind> {
+    len: usize,
+    hamt: HAMT<K, V, P>,
+    hasher: S,
+    pointer_kind: P,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, S: BuildHasher, P: PointerKind>
+    Builder<K, V, S, P>
+{
+    pub fn new(s: S, p: P) -> Self {
+        Self {
+            len: 0,
+            hamt: HAMT::new(),
+            hasher: s,
+            pointer_kind: p,
+        }
+    }
+
+    pub fn insert(&mut self, k: K, v: V) {
+        self.len += self
+            .hamt
+            .insert_mut(HashedKey::new(k, &self.hasher), v, &self.pointer_kind)
+            as usize;
+    }
+
+    pub fn get(&self, k: &K) -> Option<&V> {
+        self.hamt.get(HashedKey::new(k, &self.hasher))
+    }
+
+    pub fn build(self) -> HashMap<K, V, S, P> {
+        HashMap {
+            len: self.len,
+            hamt: self.pointer_kind.pointer(self.hamt),
+            hasher: self.hasher,
+            pointer_kind: self.pointer_kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{DefaultBuildHasher, HashMap};
+    use super::Builder;
+    use crate::pointer::ArcK;
+    use rand::random;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[test]
+    fn new() {
+        Builder::<usize, usize, _, ArcK>::new(DefaultBuildHasher::default(), ArcK);
+    }
+
+    #[test]
+    fn insert() {
+        let mut b = Builder::new(DefaultBuildHasher::default(), ArcK);
+        let mut h: HashMap<i16, i16> = HashMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let k: i16 = random();
+            let v: i16 = random();
+
+            b.insert(k, v);
+            h = h.insert(k, v);
+        }
+
+        assert_eq!(b.build(), h);
+    }
+}
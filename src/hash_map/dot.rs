@@ -0,0 +1,206 @@
+use super::bucket::Bucket;
+use super::entry::Entry;
+use super::hamt::HAMT;
+use super::HashMap;
+use crate::pointer::PointerKind;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::fmt::{Debug, Write};
+use core::hash::Hash;
+
+/// Renders hash maps in the DOT language of Graphviz.
+///
+/// Nodes shared among maps are rendered only once.
+pub fn to_dot<K: Debug + Eq + Hash, V: Debug + PartialEq, S, P: PointerKind>(
+    ms: &[&HashMap<K, V, S, P>],
+) -> String {
+    let mut w = Writer {
+        ids: BTreeMap::new(),
+        num_leaves: 0,
+        output: String::new(),
+    };
+
+    w.output.push_str("digraph {\n");
+
+    for (i, m) in ms.iter().enumerate() {
+        let id = w.write_hamt(&m.hamt);
+
+        w.write_vertex(
+            &format!("root{}", i),
+            "box",
+            &format!("root {} (len {})", i, m.len),
+        );
+        w.write_edge(&format!("root{}", i), &id, None);
+    }
+
+    w.output.push_str("}\n");
+    w.output
+}
+
+struct Writer {
+    ids: BTreeMap<*const u8, usize>,
+    num_leaves: usize,
+    output: String,
+}
+
+impl Writer {
+    fn write_hamt<K: Debug + Eq + Hash, V: Debug + PartialEq, P: PointerKind>(
+        &mut self,
+        h: &HAMT<K, V, P>,
+    ) -> String {
+        let (id, new) = self.id(h as *const _ as *const u8);
+
+        if !new {
+            return id;
+        }
+
+        self.write_vertex(&id, "circle", "");
+
+        for (i, e) in h.entries().iter().enumerate() {
+            let child = match e {
+                Entry::Empty => continue,
+                Entry::KeyValue(k, v) => self.write_leaf(k, v),
+                Entry::HAMT(h) => self.write_hamt(h),
+                Entry::Bucket(b) => self.write_bucket(b),
+            };
+
+            self.write_edge(&id, &child, Some(i));
+        }
+
+        id
+    }
+
+    fn write_bucket<K: Debug + Eq + Hash, V: Debug + PartialEq, P: PointerKind>(
+        &mut self,
+        b: &Bucket<K, V, P>,
+    ) -> String {
+        let (id, new) = self.id(b as *const _ as *const u8);
+
+        if !new {
+            return id;
+        }
+
+        self.write_vertex(&id, "box", "bucket");
+
+        for (k, v) in b {
+            let child = self.write_leaf(k, v);
+            self.write_edge(&id, &child, None);
+        }
+
+        id
+    }
+
+    fn write_leaf<K: Debug, V: Debug>(&mut self, k: &K, v: &V) -> String {
+        let id = format!("leaf{}", self.num_leaves);
+        self.num_leaves += 1;
+
+        self.write_vertex(&id, "plaintext", &format!("{:?}: {:?}", k, v));
+
+        id
+    }
+
+    fn write_vertex(&mut self, id: &str, shape: &str, label: &str) {
+        writeln!(
+            self.output,
+            "  {} [shape={}, label=\"{}\"];",
+            id,
+            shape,
+            escape(label)
+        )
+        .unwrap();
+    }
+
+    fn write_edge(&mut self, from: &str, to: &str, label: Option<usize>) {
+        match label {
+            Some(l) => writeln!(self.output, "  {} -> {} [label=\"{}\"];", from, to, l),
+            None => writeln!(self.output, "  {} -> {};", from, to),
+        }
+        .unwrap();
+    }
+
+    fn id(&mut self, address: *const u8) -> (String, bool) {
+        let n = self.ids.len();
+        let mut new = false;
+        let i = *self.ids.entry(address).or_insert_with(|| {
+            new = true;
+            n
+        });
+
+        (format!("node{}", i), new)
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::HashMap;
+    use core::hash::{Hash, Hasher};
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct CollidingKey(u8);
+
+    impl Hash for CollidingKey {
+        fn hash<H: Hasher>(&self, h: &mut H) {
+            0.hash(h)
+        }
+    }
+
+    #[test]
+    fn to_dot_empty() {
+        assert_eq!(
+            HashMap::<usize, usize>::new().to_dot(),
+            r#"digraph {
+  node0 [shape=circle, label=""];
+  root0 [shape=box, label="root 0 (len 0)"];
+  root0 -> node0;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn to_dot() {
+        let d = HashMap::<_, _>::new().insert("foo", 0).to_dot();
+
+        assert!(d.contains(r#"label="\"foo\": 0""#));
+        assert_eq!(d.matches("->").count(), 2);
+    }
+
+    #[test]
+    fn to_dot_with_buckets() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..4 {
+            h = h.insert(CollidingKey(i), i);
+        }
+
+        let d = h.to_dot();
+
+        assert_eq!(d.matches("label=\"bucket\"").count(), 1);
+        assert_eq!(d.matches("leaf").count(), 8);
+    }
+
+    #[test]
+    fn to_dot_many() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..1000 {
+            h = h.insert(i, i);
+        }
+
+        let hh = h.insert(1000, 1000);
+        let d = HashMap::to_dot_many(&[&h, &hh]);
+
+        assert!(d.contains("root1"));
+        assert!(d.len() < 2 * h.to_dot().len());
+        assert_eq!(
+            HashMap::to_dot_many(&[&h, &h])
+                .matches("[shape=circle")
+                .count(),
+            h.stats().hamt_nodes.iter().sum::<usize>()
+        );
+    }
+}
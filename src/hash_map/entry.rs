@@ -1,38 +1,74 @@
 use super::bucket::Bucket;
 use super::hamt::HAMT;
 use super::node::Node;
-use std::hash::Hash;
-use std::sync::Arc;
+use crate::pointer::PointerKind;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::Hash;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum Entry<K: Eq + Hash, V: PartialEq> {
+#[derive(Default)]
+pub enum Entry<K: Eq + Hash, V, P: PointerKind> {
+    #[default]
     Empty,
     KeyValue(K, V),
-    HAMT(Arc<HAMT<K, V>>),
-    Bucket(Arc<Bucket<K, V>>),
+    HAMT(P::Pointer<HAMT<K, V, P>>),
+    Bucket(P::Pointer<Bucket<K, V, P>>),
 }
 
-impl<K: Eq + Hash, V: PartialEq> Default for Entry<K, V> {
-    fn default() -> Self {
-        Entry::Empty
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, P: PointerKind> Entry<K, V, P> {
+    pub fn from_hamt(h: HAMT<K, V, P>, p: &P) -> Self {
+        convert_node_to_key_value(&h).unwrap_or_else(|| Entry::HAMT(p.pointer(h)))
+    }
+
+    pub fn from_bucket(b: Bucket<K, V, P>, p: &P) -> Self {
+        convert_node_to_key_value(&b).unwrap_or_else(|| Entry::Bucket(p.pointer(b)))
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, P: PointerKind> Clone for Entry<K, V, P> {
+    fn clone(&self) -> Self {
+        match self {
+            Entry::Empty => Entry::Empty,
+            Entry::KeyValue(k, v) => Entry::KeyValue(k.clone(), v.clone()),
+            Entry::HAMT(h) => Entry::HAMT(h.clone()),
+            Entry::Bucket(b) => Entry::Bucket(b.clone()),
+        }
     }
 }
 
-impl<K: Clone + Eq + Hash, V: Clone + PartialEq> From<HAMT<K, V>> for Entry<K, V> {
-    fn from(h: HAMT<K, V>) -> Self {
-        convert_node_to_key_value(&h).unwrap_or_else(|| Entry::HAMT(h.into()))
+impl<K: Eq + Hash, V: PartialEq, P: PointerKind> PartialEq for Entry<K, V, P> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Entry::Empty, Entry::Empty) => true,
+            (Entry::KeyValue(k, v), Entry::KeyValue(kk, vv)) => k == kk && v == vv,
+            (Entry::HAMT(h), Entry::HAMT(hh)) => P::ptr_eq(h, hh) || **h == **hh,
+            (Entry::Bucket(b), Entry::Bucket(bb)) => P::ptr_eq(b, bb) || **b == **bb,
+            _ => false,
+        }
     }
 }
 
-impl<K: Clone + Eq + Hash, V: Clone + PartialEq> From<Bucket<K, V>> for Entry<K, V> {
-    fn from(b: Bucket<K, V>) -> Self {
-        convert_node_to_key_value(&b).unwrap_or_else(|| Entry::Bucket(b.into()))
+impl<K: Eq + Hash, V: Eq, P: PointerKind> Eq for Entry<K, V, P> {}
+
+impl<K: Debug + Eq + Hash, V: Debug, P: PointerKind> Debug for Entry<K, V, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Entry::Empty => write!(f, "Empty"),
+            Entry::KeyValue(k, v) => f.debug_tuple("KeyValue").field(k).field(v).finish(),
+            Entry::HAMT(h) => f.debug_tuple("HAMT").field(&**h).finish(),
+            Entry::Bucket(b) => f.debug_tuple("Bucket").field(&**b).finish(),
+        }
     }
 }
 
-fn convert_node_to_key_value<'a, K: 'a + Clone + Eq + Hash, V: 'a + Clone + PartialEq, N: Node>(
+fn convert_node_to_key_value<
+    'a,
+    K: 'a + Clone + Eq + Hash,
+    V: 'a + Clone + PartialEq,
+    P: PointerKind,
+    N: Node,
+>(
     n: &'a N,
-) -> Option<Entry<K, V>>
+) -> Option<Entry<K, V, P>>
 where
     &'a N: IntoIterator<Item = (&'a K, &'a V)>,
 {
@@ -2,74 +2,90 @@ use super::bucket::{Bucket, BucketIterator};
 use super::entry::Entry;
 use super::hashed_key::HashedKey;
 use super::node::Node;
-use std::borrow::Borrow;
-use std::hash::Hash;
-
-const MAX_LEVEL: u8 = 64 / 5;
-const NUM_ENTRIES: usize = 32;
-
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct HAMT<K: Eq + Hash, V: PartialEq> {
+use super::visitor::NodeVisitor;
+use crate::pointer::PointerKind;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{BuildHasher, Hash};
+use core::mem;
+
+pub const MAX_LEVEL: u8 = 64 / 5;
+pub const NUM_ENTRIES: usize = 32;
+
+pub struct HAMT<K: Eq + Hash, V, P: PointerKind> {
     // TODO: Use bitmap.
-    entries: [Entry<K, V>; NUM_ENTRIES],
+    entries: [Entry<K, V, P>; NUM_ENTRIES],
 }
 
-impl<K: Clone + Hash + Eq, V: Clone + PartialEq> HAMT<K, V> {
+impl<K: Clone + Hash + Eq, V: Clone + PartialEq, P: PointerKind> HAMT<K, V, P> {
     pub fn new() -> Self {
         Self {
             entries: Default::default(),
         }
     }
 
-    pub fn insert(&self, hk: HashedKey<K>, v: V) -> (Self, bool) {
+    pub fn from_entries(es: [Entry<K, V, P>; NUM_ENTRIES]) -> Self {
+        Self { entries: es }
+    }
+
+    pub fn insert<S: BuildHasher>(&self, hk: HashedKey<K, S>, v: V, p: &P) -> (Self, bool) {
         let i = hk.entry_index();
 
         match &self.entries[i] {
-            Entry::Empty => (self.set_entry(i, Entry::KeyValue(hk.to_key(), v)), true),
+            Entry::Empty => (self.set_entry(i, Entry::KeyValue(hk.into_key(), v)), true),
             Entry::KeyValue(kk, vv) => {
                 if kk == hk.key() {
-                    (self.set_entry(i, Entry::KeyValue(hk.to_key(), v)), false)
+                    (self.set_entry(i, Entry::KeyValue(hk.into_key(), v)), false)
                 } else {
                     (
-                        self.set_entry(
-                            i,
-                            if hk.level() < MAX_LEVEL {
-                                Entry::HAMT(
-                                    Self::new()
-                                        .insert(
-                                            hk.swap_key(kk.clone()).increment_level(),
-                                            vv.clone(),
-                                        )
-                                        .0
-                                        .insert(hk.increment_level(), v)
-                                        .0
-                                        .into(),
-                                )
-                            } else {
-                                Entry::Bucket(
-                                    Bucket::new(kk.clone(), vv.clone())
-                                        .insert(hk.to_key(), v)
-                                        .0
-                                        .into(),
-                                )
-                            },
-                        ),
+                        self.set_entry(i, Self::split_entry(hk, kk.clone(), vv.clone(), v, p)),
                         true,
                     )
                 }
             }
             Entry::HAMT(h) => {
-                let (h, new) = h.insert(hk.increment_level(), v);
-                (self.set_entry(i, Entry::HAMT(h.into())), new)
+                let (h, new) = h.insert(hk.increment_level(), v, p);
+                (self.set_entry(i, Entry::HAMT(p.pointer(h))), new)
             }
             Entry::Bucket(b) => {
-                let (b, new) = b.insert(hk.to_key(), v);
-                (self.set_entry(i, Entry::Bucket(b.into())), new)
+                let (b, new) = b.insert(hk.into_key(), v, p);
+                (self.set_entry(i, Entry::Bucket(p.pointer(b))), new)
             }
         }
     }
 
-    pub fn remove<Q: ?Sized + Eq + Hash>(&self, hk: HashedKey<&Q>) -> Option<Self>
+    pub fn insert_mut<S: BuildHasher>(&mut self, hk: HashedKey<K, S>, v: V, p: &P) -> bool {
+        let i = hk.entry_index();
+
+        let (e, new) = match mem::take(&mut self.entries[i]) {
+            Entry::Empty => (Entry::KeyValue(hk.into_key(), v), true),
+            Entry::KeyValue(kk, vv) => {
+                if &kk == hk.key() {
+                    (Entry::KeyValue(hk.into_key(), v), false)
+                } else {
+                    (Self::split_entry(hk, kk, vv, v, p), true)
+                }
+            }
+            Entry::HAMT(mut h) => {
+                let new = p.make_mut(&mut h).insert_mut(hk.increment_level(), v, p);
+                (Entry::HAMT(h), new)
+            }
+            Entry::Bucket(mut b) => {
+                let new = p.make_mut(&mut b).insert_mut(hk.into_key(), v, p);
+                (Entry::Bucket(b), new)
+            }
+        };
+
+        self.entries[i] = e;
+        new
+    }
+
+    pub fn remove<Q: ?Sized + Eq + Hash, S: BuildHasher>(
+        &self,
+        hk: HashedKey<&Q, S>,
+        p: &P,
+    ) -> Option<Self>
     where
         K: Borrow<Q>,
     {
@@ -86,38 +102,207 @@ impl<K: Clone + Hash + Eq, V: Clone + PartialEq> HAMT<K, V> {
                         return None;
                     }
                 }
-                Entry::HAMT(h) => match h.remove(hk.increment_level()) {
-                    None => return None,
-                    Some(h) => h.into(),
-                },
-                Entry::Bucket(b) => match b.remove(hk.key()) {
-                    None => return None,
-                    Some(b) => b.into(),
-                },
+                Entry::HAMT(h) => Entry::from_hamt(h.remove(hk.increment_level(), p)?, p),
+                Entry::Bucket(b) => Entry::from_bucket(b.remove(hk.key(), p)?, p),
             },
         )
         .into()
     }
 
-    pub fn get<Q: ?Sized + Eq + Hash>(&self, hk: HashedKey<&Q>) -> Option<&V>
-    where
-        K: Borrow<Q>,
-    {
-        match &self.entries[hk.entry_index()] {
-            Entry::Empty => None,
+    /// Updates an entry of a key in a single traversal, returning a change of
+    /// the number of entries.
+    pub fn alter<S: BuildHasher>(
+        &self,
+        hk: HashedKey<K, S>,
+        f: impl FnOnce(Option<&V>) -> Option<V>,
+        p: &P,
+    ) -> (Self, isize) {
+        let i = hk.entry_index();
+
+        let (e, d) = match &self.entries[i] {
+            Entry::Empty => match f(None) {
+                None => return (self.clone(), 0),
+                Some(v) => (Entry::KeyValue(hk.into_key(), v), 1),
+            },
             Entry::KeyValue(kk, vv) => {
-                if &kk.borrow() == hk.key() {
-                    Some(vv)
+                if kk == hk.key() {
+                    match f(Some(vv)) {
+                        None => (Entry::Empty, -1),
+                        Some(v) => (Entry::KeyValue(hk.into_key(), v), 0),
+                    }
                 } else {
-                    None
+                    match f(None) {
+                        None => return (self.clone(), 0),
+                        Some(v) => (Self::split_entry(hk, kk.clone(), vv.clone(), v, p), 1),
+                    }
+                }
+            }
+            Entry::HAMT(h) => {
+                let (h, d) = h.alter(hk.increment_level(), f, p);
+                (Entry::from_hamt(h, p), d)
+            }
+            Entry::Bucket(b) => {
+                let (b, d) = b.alter(hk.into_key(), f, p);
+                (Entry::from_bucket(b, p), d)
+            }
+        };
+
+        (self.set_entry(i, e), d)
+    }
+
+    /// Merges two HAMTs at the same level node by node.
+    ///
+    /// Values of keys in both HAMTs are combined by a function. Keys in only
+    /// one of them are kept for unions and dropped for intersections. Nodes in
+    /// only one HAMT for unions and nodes shared by both for intersections are
+    /// reused, so the function must return an equal value for equal values
+    /// on intersections.
+    pub fn merge<S: BuildHasher>(
+        &self,
+        other: &Self,
+        level: u8,
+        union: bool,
+        f: &mut impl FnMut(&V, &V) -> Option<V>,
+        s: &S,
+        p: &P,
+    ) -> Self {
+        let mut h = Self::new();
+
+        for (i, (e, ee)) in self.entries.iter().zip(&other.entries).enumerate() {
+            h.entries[i] = Self::merge_entries(e, ee, level, union, f, s, p);
+        }
+
+        h
+    }
+
+    fn merge_entries<S: BuildHasher>(
+        e: &Entry<K, V, P>,
+        ee: &Entry<K, V, P>,
+        level: u8,
+        union: bool,
+        f: &mut impl FnMut(&V, &V) -> Option<V>,
+        s: &S,
+        p: &P,
+    ) -> Entry<K, V, P> {
+        match (e, ee) {
+            (Entry::Empty, e) | (e, Entry::Empty) => {
+                if union {
+                    e.clone()
+                } else {
+                    Entry::Empty
                 }
             }
-            Entry::HAMT(h) => h.get(hk.increment_level()),
-            Entry::Bucket(b) => b.get(hk.key()),
+            (Entry::HAMT(h), Entry::HAMT(hh)) => {
+                if !union && P::ptr_eq(h, hh) {
+                    for (_, v) in &**h {
+                        f(v, v);
+                    }
+
+                    return e.clone();
+                }
+
+                let h = h.merge(hh, level + 1, union, f, s, p);
+
+                if h.is_empty() {
+                    Entry::Empty
+                } else {
+                    Entry::from_hamt(h, p)
+                }
+            }
+            (Entry::Bucket(b), Entry::Bucket(bb)) => {
+                if !union && P::ptr_eq(b, bb) {
+                    for (_, v) in &**b {
+                        f(v, v);
+                    }
+
+                    return e.clone();
+                }
+
+                let b = b.merge(bb, union, f, p);
+
+                if b.len() == 0 {
+                    Entry::Empty
+                } else {
+                    Entry::from_bucket(b, p)
+                }
+            }
+            (Entry::KeyValue(k, v), e) => {
+                Self::merge_key_value(HashedKey::with_level(k, level, s), v, e, union, f, p)
+            }
+            (e, Entry::KeyValue(k, v)) => {
+                let hk = HashedKey::with_level(k, level, s);
+                Self::merge_key_value(hk, v, e, union, &mut |v, w| f(w, v), p)
+            }
+            (Entry::HAMT(_), Entry::Bucket(_)) | (Entry::Bucket(_), Entry::HAMT(_)) => {
+                unreachable!("sub-HAMT and bucket at the same level")
+            }
         }
     }
 
-    fn set_entry(&self, i: usize, e: Entry<K, V>) -> Self {
+    // Merges a key-value pair into an entry of another HAMT.
+    fn merge_key_value<S: BuildHasher>(
+        hk: HashedKey<&K, S>,
+        v: &V,
+        e: &Entry<K, V, P>,
+        union: bool,
+        f: &mut impl FnMut(&V, &V) -> Option<V>,
+        p: &P,
+    ) -> Entry<K, V, P> {
+        let k = *hk.key();
+        let key_value = |x: Option<V>| x.map_or(Entry::Empty, |x| Entry::KeyValue(k.clone(), x));
+        let g = |w: Option<&V>| w.map_or_else(|| Some(v.clone()), |w| f(v, w));
+
+        match e {
+            Entry::Empty => key_value(union.then(|| v.clone())),
+            Entry::KeyValue(kk, vv) => {
+                if kk == k {
+                    key_value(f(v, vv))
+                } else if union {
+                    Self::split_entry(hk.cloned(), kk.clone(), vv.clone(), v.clone(), p)
+                } else {
+                    Entry::Empty
+                }
+            }
+            Entry::HAMT(h) => {
+                if union {
+                    Entry::from_hamt(h.alter(hk.cloned().increment_level(), g, p).0, p)
+                } else {
+                    key_value(h.get(hk.increment_level()).and_then(|w| f(v, w)))
+                }
+            }
+            Entry::Bucket(b) => {
+                if union {
+                    Entry::from_bucket(b.alter(k.clone(), g, p).0, p)
+                } else {
+                    key_value(b.get(k).and_then(|w| f(v, w)))
+                }
+            }
+        }
+    }
+
+    fn split_entry<S: BuildHasher>(
+        hk: HashedKey<K, S>,
+        kk: K,
+        vv: V,
+        v: V,
+        p: &P,
+    ) -> Entry<K, V, P> {
+        if hk.level() < MAX_LEVEL {
+            let mut h = Self::new();
+
+            h.insert_mut(hk.swap_key(kk).increment_level(), vv, p);
+            h.insert_mut(hk.increment_level(), v, p);
+
+            Entry::HAMT(p.pointer(h))
+        } else {
+            let mut b = Bucket::new(kk, vv, p);
+            b.insert_mut(hk.into_key(), v, p);
+
+            Entry::Bucket(p.pointer(b))
+        }
+    }
+
+    fn set_entry(&self, i: usize, e: Entry<K, V, P>) -> Self {
         let mut es = self.entries.clone();
         es[i] = e;
 
@@ -139,10 +324,7 @@ impl<K: Clone + Hash + Eq, V: Clone + PartialEq> HAMT<K, V> {
 
     #[cfg(test)]
     fn contain_bucket(&self) -> bool {
-        self.entries.iter().any(|e| match e {
-            Entry::Bucket(_) => true,
-            _ => false,
-        })
+        self.entries.iter().any(|e| matches!(e, Entry::Bucket(_)))
     }
 
     #[cfg(test)]
@@ -155,7 +337,107 @@ impl<K: Clone + Hash + Eq, V: Clone + PartialEq> HAMT<K, V> {
     }
 }
 
-impl<K: Eq + Hash, V: PartialEq> Node for HAMT<K, V> {
+impl<K: Eq + Hash, V: PartialEq, P: PointerKind> HAMT<K, V, P> {
+    pub fn get<Q: ?Sized + Eq + Hash, S: BuildHasher>(&self, hk: HashedKey<&Q, S>) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.get_key_value(hk).map(|(_, v)| v)
+    }
+
+    pub fn get_key_value<Q: ?Sized + Eq + Hash, S: BuildHasher>(
+        &self,
+        hk: HashedKey<&Q, S>,
+    ) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        match &self.entries[hk.entry_index()] {
+            Entry::Empty => None,
+            Entry::KeyValue(kk, vv) => {
+                if &kk.borrow() == hk.key() {
+                    Some((kk, vv))
+                } else {
+                    None
+                }
+            }
+            Entry::HAMT(h) => h.get_key_value(hk.increment_level()),
+            Entry::Bucket(b) => b.get_key_value(hk.key()),
+        }
+    }
+
+    pub fn entries(&self) -> &[Entry<K, V, P>; NUM_ENTRIES] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(|e| matches!(e, Entry::Empty))
+    }
+
+    pub fn visit_nodes<N: NodeVisitor<K, V>>(&self, n: &mut N) {
+        if !n.visit_hamt(self as *const Self as *const u8, mem::size_of::<Self>()) {
+            return;
+        }
+
+        for e in &self.entries {
+            match e {
+                Entry::Empty => {}
+                Entry::KeyValue(k, v) => n.visit_key_value(k, v),
+                Entry::HAMT(h) => h.visit_nodes(n),
+                Entry::Bucket(b) => b.visit_nodes(n),
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, P: PointerKind> Drop for HAMT<K, V, P> {
+    // Sub-HAMTs are moved onto a stack and dropped one by one.
+    fn drop(&mut self) {
+        let mut hs = vec![];
+
+        Self::take_children(&mut self.entries, &mut hs);
+
+        while let Some(mut h) = hs.pop() {
+            Self::take_children(&mut h.entries, &mut hs);
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, P: PointerKind> HAMT<K, V, P> {
+    fn take_children(es: &mut [Entry<K, V, P>], hs: &mut Vec<Self>) {
+        for e in es {
+            if let Entry::HAMT(h) = mem::take(e) {
+                hs.extend(P::into_inner(h));
+            }
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, P: PointerKind> Clone for HAMT<K, V, P> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, P: PointerKind> PartialEq for HAMT<K, V, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl<K: Eq + Hash, V: Eq, P: PointerKind> Eq for HAMT<K, V, P> {}
+
+impl<K: Debug + Eq + Hash, V: Debug, P: PointerKind> Debug for HAMT<K, V, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("HAMT")
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, P: PointerKind> Node for HAMT<K, V, P> {
     fn is_singleton(&self) -> bool {
         let mut sum = 0;
 
@@ -173,13 +455,13 @@ impl<K: Eq + Hash, V: PartialEq> Node for HAMT<K, V> {
 }
 
 #[derive(Clone, Debug)]
-pub struct HAMTIterator<'a, K: 'a + Eq + Hash, V: 'a + PartialEq> {
-    hamts: Vec<(&'a HAMT<K, V>, usize)>,
+pub struct HAMTIterator<'a, K: 'a + Eq + Hash, V: 'a, P: PointerKind> {
+    hamts: Vec<(&'a HAMT<K, V, P>, usize)>,
     bucket_iterator: Option<BucketIterator<'a, K, V>>,
 }
 
-impl<'a, K: Eq + Hash, V: PartialEq> IntoIterator for &'a HAMT<K, V> {
-    type IntoIter = HAMTIterator<'a, K, V>;
+impl<'a, K: Eq + Hash, V: PartialEq, P: PointerKind> IntoIterator for &'a HAMT<K, V, P> {
+    type IntoIter = HAMTIterator<'a, K, V, P>;
     type Item = (&'a K, &'a V);
 
     fn into_iter(self) -> Self::IntoIter {
@@ -190,7 +472,7 @@ impl<'a, K: Eq + Hash, V: PartialEq> IntoIterator for &'a HAMT<K, V> {
     }
 }
 
-impl<'a, K: Eq + Hash, V: PartialEq> Iterator for HAMTIterator<'a, K, V> {
+impl<'a, K: Eq + Hash, V: PartialEq, P: PointerKind> Iterator for HAMTIterator<'a, K, V, P> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -226,12 +508,18 @@ impl<'a, K: Eq + Hash, V: PartialEq> Iterator for HAMTIterator<'a, K, V> {
 #[cfg(test)]
 mod test {
     use super::super::hashed_key::HashedKey;
-    use super::{HAMT, MAX_LEVEL};
+    use super::super::DefaultBuildHasher;
+    use super::MAX_LEVEL;
+    use crate::pointer::ArcK;
+    use core::hash::BuildHasherDefault;
     use rand::{random, seq::SliceRandom, thread_rng};
     use std::collections::HashMap;
-    use test::Bencher;
+    use std::vec::Vec;
 
     const NUM_ITERATIONS: usize = 1 << 12;
+    const S: DefaultBuildHasher = BuildHasherDefault::new();
+
+    type HAMT<K, V> = super::HAMT<K, V, ArcK>;
 
     #[test]
     fn new() {
@@ -244,17 +532,17 @@ mod test {
 
         assert_eq!(h.len(), 0);
 
-        let (h, b) = h.insert(HashedKey::new(0), 0);
+        let (h, b) = h.insert(HashedKey::new(0, &S), 0, &ArcK);
 
         assert!(b);
         assert_eq!(h.len(), 1);
 
-        let (hh, b) = h.insert(HashedKey::new(0), 0);
+        let (hh, b) = h.insert(HashedKey::new(0, &S), 0, &ArcK);
 
         assert!(!b);
         assert_eq!(hh.len(), 1);
 
-        let (h, b) = h.insert(HashedKey::new(1), 0);
+        let (h, b) = h.insert(HashedKey::new(1, &S), 0, &ArcK);
 
         assert!(b);
         assert_eq!(h.len(), 2);
@@ -265,7 +553,7 @@ mod test {
         let mut h = HAMT::new();
 
         for i in 0..NUM_ITERATIONS {
-            let (hh, b) = h.insert(HashedKey::new(i), i);
+            let (hh, b) = h.insert(HashedKey::new(i, &S), i, &ArcK);
             h = hh;
             assert!(b);
             assert_eq!(h.len(), i + 1);
@@ -278,7 +566,7 @@ mod test {
 
         for i in 0..NUM_ITERATIONS {
             let k = random();
-            h = h.insert(HashedKey::new(k), k).0;
+            h = h.insert(HashedKey::new(k, &S), k, &ArcK).0;
             assert_eq!(h.len(), i + 1);
         }
     }
@@ -288,35 +576,39 @@ mod test {
         let h = HAMT::new();
 
         assert_eq!(
-            h.insert(HashedKey::new(0), 0).0.remove(HashedKey::new(&0)),
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
+                .0
+                .remove(HashedKey::new(&0, &S), &ArcK),
             Some(h.clone())
         );
         assert_eq!(
-            h.insert(HashedKey::new(0), 0).0.remove(HashedKey::new(&1)),
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
+                .0
+                .remove(HashedKey::new(&1, &S), &ArcK),
             None
         );
         assert_eq!(
-            h.insert(HashedKey::new(0), 0)
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
                 .0
-                .insert(HashedKey::new(1), 0)
+                .insert(HashedKey::new(1, &S), 0, &ArcK)
                 .0
-                .remove(HashedKey::new(&0)),
-            Some(h.insert(HashedKey::new(1), 0).0)
+                .remove(HashedKey::new(&0, &S), &ArcK),
+            Some(h.insert(HashedKey::new(1, &S), 0, &ArcK).0)
         );
         assert_eq!(
-            h.insert(HashedKey::new(0), 0)
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
                 .0
-                .insert(HashedKey::new(1), 0)
+                .insert(HashedKey::new(1, &S), 0, &ArcK)
                 .0
-                .remove(HashedKey::new(&1)),
-            Some(h.insert(HashedKey::new(0), 0).0)
+                .remove(HashedKey::new(&1, &S), &ArcK),
+            Some(h.insert(HashedKey::new(0, &S), 0, &ArcK).0)
         );
         assert_eq!(
-            h.insert(HashedKey::new(0), 0)
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
                 .0
-                .insert(HashedKey::new(1), 0)
+                .insert(HashedKey::new(1, &S), 0, &ArcK)
                 .0
-                .remove(HashedKey::new(&2)),
+                .remove(HashedKey::new(&2, &S), &ArcK),
             None
         );
     }
@@ -328,18 +620,18 @@ mod test {
         for _ in 0..NUM_ITERATIONS {
             let k = random();
             let s = h.len();
-            let found = h.get(HashedKey::new(&k)).is_some();
+            let found = h.get(HashedKey::new(&k, &S)).is_some();
 
             if random() {
-                h = h.insert(HashedKey::new(k), k).0;
+                h = h.insert(HashedKey::new(k, &S), k, &ArcK).0;
 
                 assert_eq!(h.len(), if found { s } else { s + 1 });
-                assert_eq!(h.get(HashedKey::new(&k)), Some(&k));
+                assert_eq!(h.get(HashedKey::new(&k, &S)), Some(&k));
             } else {
-                h = h.remove(HashedKey::new(&k)).unwrap_or(h);
+                h = h.remove(HashedKey::new(&k, &S), &ArcK).unwrap_or(h);
 
                 assert_eq!(h.len(), if found { s - 1 } else { s });
-                assert_eq!(h.get(HashedKey::new(&k)), None);
+                assert_eq!(h.get(HashedKey::new(&k, &S)), None);
             }
 
             assert!(h.is_normal());
@@ -351,43 +643,51 @@ mod test {
         let h = HAMT::new();
 
         assert_eq!(
-            h.insert(HashedKey::new(0), 0).0.get(HashedKey::new(&0)),
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
+                .0
+                .get(HashedKey::new(&0, &S)),
             Some(&0)
         );
         assert_eq!(
-            h.insert(HashedKey::new(0), 0).0.get(HashedKey::new(&1)),
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
+                .0
+                .get(HashedKey::new(&1, &S)),
             None
         );
         assert_eq!(
-            h.insert(HashedKey::new(1), 0).0.get(HashedKey::new(&0)),
+            h.insert(HashedKey::new(1, &S), 0, &ArcK)
+                .0
+                .get(HashedKey::new(&0, &S)),
             None
         );
         assert_eq!(
-            h.insert(HashedKey::new(1), 0).0.get(HashedKey::new(&1)),
+            h.insert(HashedKey::new(1, &S), 0, &ArcK)
+                .0
+                .get(HashedKey::new(&1, &S)),
             Some(&0)
         );
         assert_eq!(
-            h.insert(HashedKey::new(0), 0)
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
                 .0
-                .insert(HashedKey::new(1), 0)
+                .insert(HashedKey::new(1, &S), 0, &ArcK)
                 .0
-                .get(HashedKey::new(&0)),
+                .get(HashedKey::new(&0, &S)),
             Some(&0)
         );
         assert_eq!(
-            h.insert(HashedKey::new(0), 0)
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
                 .0
-                .insert(HashedKey::new(1), 0)
+                .insert(HashedKey::new(1, &S), 0, &ArcK)
                 .0
-                .get(HashedKey::new(&1)),
+                .get(HashedKey::new(&1, &S)),
             Some(&0)
         );
         assert_eq!(
-            h.insert(HashedKey::new(0), 0)
+            h.insert(HashedKey::new(0, &S), 0, &ArcK)
                 .0
-                .insert(HashedKey::new(1), 0)
+                .insert(HashedKey::new(1, &S), 0, &ArcK)
                 .0
-                .get(HashedKey::new(&2)),
+                .get(HashedKey::new(&2, &S)),
             None
         );
     }
@@ -404,11 +704,11 @@ mod test {
                 ds.shuffle(&mut thread_rng());
 
                 for i in &is {
-                    *h = h.insert(HashedKey::new(*i), *i).0;
+                    *h = h.insert(HashedKey::new(*i, &S), *i, &ArcK).0;
                 }
 
                 for d in &ds {
-                    *h = h.remove(HashedKey::new(&d)).unwrap_or(h.clone());
+                    *h = h.remove(HashedKey::new(d, &S), &ArcK).unwrap_or(h.clone());
                 }
             }
 
@@ -421,13 +721,13 @@ mod test {
         let mut h = HAMT::new();
 
         for k in 0..33 {
-            let mut hk = HashedKey::new(k);
+            let mut hk = HashedKey::new(k, &S);
 
             for _ in 0..MAX_LEVEL {
                 hk = hk.increment_level()
             }
 
-            h = h.insert(hk, k).0;
+            h = h.insert(hk, k, &ArcK).0;
         }
 
         assert!(h.contain_bucket());
@@ -441,7 +741,7 @@ mod test {
             ss.push(random::<usize>() % 1024);
         }
 
-        for l in vec![0, MAX_LEVEL] {
+        for l in [0, MAX_LEVEL] {
             for s in &ss {
                 let mut h: HAMT<i16, i16> = HAMT::new();
                 let mut m: HashMap<i16, i16> = HashMap::new();
@@ -450,13 +750,13 @@ mod test {
                     let k = random();
                     let v = random();
 
-                    let mut hk = HashedKey::new(k);
+                    let mut hk = HashedKey::new(k, &S);
 
                     for _ in 0..l {
                         hk = hk.increment_level()
                     }
 
-                    let (hh, _) = h.insert(hk, v);
+                    let (hh, _) = h.insert(hk, v, &ArcK);
                     h = hh;
 
                     m.insert(k, v);
@@ -482,17 +782,85 @@ mod test {
         let mut h = HAMT::new();
 
         for k in &ks {
-            let mut hk = HashedKey::new(k);
+            let mut hk = HashedKey::new(k, &S);
 
             for _ in 0..MAX_LEVEL {
                 hk = hk.increment_level()
             }
-            h = h.insert(hk, k).0;
+            h = h.insert(hk, k, &ArcK).0;
         }
 
         assert_eq!(ks.len(), h.into_iter().collect::<Vec<_>>().len())
     }
 
+    #[test]
+    fn alter() {
+        let mut h: HAMT<i16, i16> = HAMT::new();
+        let mut m = HashMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let k = random();
+            let v = if random() { Some(random()) } else { None };
+            let (hh, d) = h.alter(
+                HashedKey::new(k, &S),
+                |vv| {
+                    assert_eq!(vv, m.get(&k));
+                    v
+                },
+                &ArcK,
+            );
+            let n = m.len() as isize;
+
+            match v {
+                Some(v) => m.insert(k, v),
+                None => m.remove(&k),
+            };
+
+            h = hh;
+
+            assert_eq!(d, m.len() as isize - n);
+            assert_eq!(h.len(), m.len());
+            assert_eq!(h.get(HashedKey::new(&k, &S)), m.get(&k));
+            assert!(h.is_normal());
+        }
+    }
+
+    #[test]
+    fn drop_shared() {
+        let mut h = HAMT::new();
+
+        for i in 0..NUM_ITERATIONS {
+            h = h.insert(HashedKey::new(i, &S), i, &ArcK).0;
+        }
+
+        let hh = h
+            .insert(HashedKey::new(NUM_ITERATIONS, &S), NUM_ITERATIONS, &ArcK)
+            .0;
+
+        drop(h);
+
+        assert_eq!(hh.len(), NUM_ITERATIONS + 1);
+
+        for i in 0..=NUM_ITERATIONS {
+            assert_eq!(hh.get(HashedKey::new(&i, &S)), Some(&i));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod bench {
+    use super::super::hashed_key::HashedKey;
+    use super::super::DefaultBuildHasher;
+    use crate::pointer::ArcK;
+    use core::hash::BuildHasherDefault;
+    use std::collections::HashMap;
+    use std::vec::Vec;
+    use test::Bencher;
+
+    const S: DefaultBuildHasher = BuildHasherDefault::new();
+
+    type HAMT<K, V> = super::HAMT<K, V, ArcK>;
+
     fn keys() -> Vec<i16> {
         (0..1000).collect()
     }
@@ -505,7 +873,7 @@ mod test {
             let mut h = HAMT::new();
 
             for k in &ks {
-                h = h.insert(HashedKey::new(k), k).0;
+                h = h.insert(HashedKey::new(k, &S), k, &ArcK).0;
             }
         });
     }
@@ -516,12 +884,12 @@ mod test {
         let mut h = HAMT::new();
 
         for k in &ks {
-            h = h.insert(HashedKey::new(k), k).0;
+            h = h.insert(HashedKey::new(k, &S), k, &ArcK).0;
         }
 
         b.iter(|| {
             for k in &ks {
-                h.get(HashedKey::new(&k));
+                h.get(HashedKey::new(&k, &S));
             }
         });
     }
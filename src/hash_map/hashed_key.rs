@@ -1,19 +1,30 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use core::hash::{BuildHasher, Hash};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct HashedKey<K> {
+pub struct HashedKey<'a, K, S> {
     key: K,
     hash: u64,
     level: u8,
+    hasher: &'a S,
 }
 
-impl<K: Hash> HashedKey<K> {
-    pub fn new(k: K) -> Self {
+impl<'a, K: Hash, S: BuildHasher> HashedKey<'a, K, S> {
+    pub fn new(k: K, s: &'a S) -> Self {
         Self {
-            hash: Self::hash(&k),
+            hash: s.hash_one(&k),
             key: k,
             level: 0,
+            hasher: s,
+        }
+    }
+
+    /// Creates a hashed key whose hash is shifted for a level.
+    pub fn with_level(k: K, level: u8, s: &'a S) -> Self {
+        Self {
+            hash: s.hash_one(&k) >> (level * 5),
+            key: k,
+            level,
+            hasher: s,
         }
     }
 
@@ -31,9 +42,10 @@ impl<K: Hash> HashedKey<K> {
 
     pub fn swap_key(&self, k: K) -> Self {
         Self {
-            hash: Self::hash(&k) >> (self.level * 5),
+            hash: self.hasher.hash_one(&k) >> (self.level * 5),
             key: k,
             level: self.level,
+            hasher: self.hasher,
         }
     }
 
@@ -42,16 +54,22 @@ impl<K: Hash> HashedKey<K> {
             key: self.key,
             hash: self.hash >> 5,
             level: self.level + 1,
+            hasher: self.hasher,
         }
     }
 
-    pub fn to_key(self) -> K {
+    pub fn into_key(self) -> K {
         self.key
     }
+}
 
-    fn hash(k: &K) -> u64 {
-        let mut h = DefaultHasher::new();
-        k.hash(&mut h);
-        h.finish()
+impl<'a, K: Clone, S> HashedKey<'a, &K, S> {
+    pub fn cloned(&self) -> HashedKey<'a, K, S> {
+        HashedKey {
+            key: self.key.clone(),
+            hash: self.hash,
+            level: self.level,
+            hasher: self.hasher,
+        }
     }
 }
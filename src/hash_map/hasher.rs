@@ -0,0 +1,141 @@
+use core::hash::{BuildHasherDefault, Hasher};
+
+/// A default hasher builder of hash maps.
+///
+/// It builds hashers with fixed keys so that maps with the same contents have
+/// the same structures in any build configuration.
+pub type DefaultBuildHasher = BuildHasherDefault<DefaultHasher>;
+
+/// A default hasher of hash maps which implements SipHash-1-3 with zero keys.
+///
+/// Unlike hashers in `std`, its algorithm is fixed so that archives and
+/// snapshots written by one build can be read by another one with or without
+/// the `std` feature.
+#[derive(Clone, Debug)]
+pub struct DefaultHasher {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    // Bytes not processed yet in little endian.
+    tail: u64,
+    length: usize,
+}
+
+impl DefaultHasher {
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        self.round();
+        self.v0 ^= m;
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+}
+
+impl Default for DefaultHasher {
+    fn default() -> Self {
+        Self {
+            v0: 0x736f6d6570736575,
+            v1: 0x646f72616e646f6d,
+            v2: 0x6c7967656e657261,
+            v3: 0x7465646279746573,
+            tail: 0,
+            length: 0,
+        }
+    }
+}
+
+impl Hasher for DefaultHasher {
+    fn write(&mut self, bs: &[u8]) {
+        for &b in bs {
+            let i = self.length % 8;
+            self.tail |= (b as u64) << (8 * i);
+            self.length += 1;
+
+            if i == 7 {
+                self.compress(self.tail);
+                self.tail = 0;
+            }
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let mut h = self.clone();
+
+        h.compress(((self.length as u64) << 56) | self.tail);
+        h.v2 ^= 0xff;
+
+        for _ in 0..3 {
+            h.round();
+        }
+
+        h.v0 ^ h.v1 ^ h.v2 ^ h.v3
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DefaultBuildHasher, DefaultHasher};
+    use core::hash::{BuildHasher, Hasher};
+
+    #[test]
+    fn hash_bytes() {
+        let mut h = DefaultHasher::default();
+        h.write(b"foo");
+        let x = h.finish();
+        h.write(b"bar");
+
+        assert_ne!(h.finish(), x);
+        assert_eq!(h.clone().finish(), h.finish());
+    }
+
+    #[test]
+    fn hash_in_pieces() {
+        let mut h = DefaultHasher::default();
+        let mut hh = DefaultHasher::default();
+
+        h.write(b"foo bar baz");
+        hh.write(b"foo");
+        hh.write(b" bar ");
+        hh.write(b"baz");
+
+        assert_eq!(h.finish(), hh.finish());
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn hash_fixed_values() {
+        let s = DefaultBuildHasher::default();
+
+        assert_eq!(s.hash_one(0u64), 0xbd60acb658c79e45);
+        assert_eq!(s.hash_one("foo"), 0x3e8b8c44c3ca73b7);
+    }
+
+    #[test]
+    fn hash_as_sip_hash_1_3() {
+        use std::collections::hash_map::DefaultHasher as StdHasher;
+        use std::string::String;
+
+        let s = DefaultBuildHasher::default();
+        let ss = std::hash::BuildHasherDefault::<StdHasher>::default();
+
+        for x in 0..1000u64 {
+            assert_eq!(s.hash_one(x), ss.hash_one(x));
+            assert_eq!(
+                s.hash_one(String::from("foo").repeat(x as usize % 7)),
+                ss.hash_one(String::from("foo").repeat(x as usize % 7))
+            );
+        }
+    }
+}
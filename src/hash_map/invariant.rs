@@ -0,0 +1,352 @@
+use super::entry::Entry;
+use super::hamt::{HAMT, MAX_LEVEL};
+use crate::pointer::PointerKind;
+use alloc::collections::BTreeMap;
+use core::fmt::{self, Display, Formatter};
+use core::hash::{BuildHasher, Hash};
+
+/// A violation of structural invariants of hash maps.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InvariantError {
+    /// A sub-HAMT has less than two key-value pairs.
+    SingletonHAMT,
+    /// A bucket has less than two key-value pairs.
+    SingletonBucket,
+    /// A sub-HAMT is at the maximum level where only buckets are allowed.
+    MisplacedHAMT,
+    /// A bucket is above the maximum level.
+    MisplacedBucket,
+    /// A key is not at the entry selected by its hash.
+    MisplacedKey,
+    /// A bucket has equal keys.
+    DuplicateKey,
+    /// A stored length differs from the number of key-value pairs.
+    LengthMismatch { expected: usize, actual: usize },
+}
+
+impl Display for InvariantError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            InvariantError::SingletonHAMT => write!(f, "singleton HAMT"),
+            InvariantError::SingletonBucket => write!(f, "singleton bucket"),
+            InvariantError::MisplacedHAMT => write!(f, "HAMT at maximum level"),
+            InvariantError::MisplacedBucket => write!(f, "bucket above maximum level"),
+            InvariantError::MisplacedKey => write!(f, "misplaced key"),
+            InvariantError::DuplicateKey => write!(f, "duplicate key"),
+            InvariantError::LengthMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "length mismatch: expected {} but got {}",
+                    expected, actual
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvariantError {}
+
+pub fn check<K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind>(
+    h: &HAMT<K, V, P>,
+    len: usize,
+    s: &S,
+) -> Result<(), InvariantError> {
+    Checker::default().check(h, len, s)
+}
+
+// A checker which checks nodes shared by maps only once.
+#[derive(Default)]
+pub struct Checker {
+    // Addresses of checked sub-HAMTs mapped to their levels, paths and lengths.
+    hamts: BTreeMap<usize, (u8, u64, usize)>,
+}
+
+impl Checker {
+    pub fn check<K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind>(
+        &mut self,
+        h: &HAMT<K, V, P>,
+        len: usize,
+        s: &S,
+    ) -> Result<(), InvariantError> {
+        let actual = self.check_hamt(h, 0, 0, s)?;
+
+        if actual == len {
+            Ok(())
+        } else {
+            Err(InvariantError::LengthMismatch {
+                expected: len,
+                actual,
+            })
+        }
+    }
+
+    fn check_hamt<K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind>(
+        &mut self,
+        h: &HAMT<K, V, P>,
+        level: u8,
+        path: u64,
+        s: &S,
+    ) -> Result<usize, InvariantError> {
+        let mut len = 0;
+
+        for (i, e) in h.entries().iter().enumerate() {
+            let path = path | ((i as u64) << (5 * level));
+
+            len += match e {
+                Entry::Empty => 0,
+                // Hashes have less than five bits left at the maximum level.
+                _ if level == MAX_LEVEL && i >> (64 - 5 * MAX_LEVEL) != 0 => {
+                    return Err(InvariantError::MisplacedKey)
+                }
+                Entry::KeyValue(k, _) => {
+                    check_key(k, level, path, s)?;
+                    1
+                }
+                Entry::HAMT(h) => {
+                    if level == MAX_LEVEL {
+                        return Err(InvariantError::MisplacedHAMT);
+                    }
+
+                    self.check_sub_hamt(h, level + 1, path, s)?
+                }
+                Entry::Bucket(b) => {
+                    if level != MAX_LEVEL {
+                        return Err(InvariantError::MisplacedBucket);
+                    } else if b.len() < 2 {
+                        return Err(InvariantError::SingletonBucket);
+                    }
+
+                    for (i, (k, _)) in b.into_iter().enumerate() {
+                        check_key(k, level, path, s)?;
+
+                        if b.into_iter().take(i).any(|(kk, _)| kk == k) {
+                            return Err(InvariantError::DuplicateKey);
+                        }
+                    }
+
+                    b.len()
+                }
+            };
+        }
+
+        Ok(len)
+    }
+
+    fn check_sub_hamt<K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind>(
+        &mut self,
+        h: &HAMT<K, V, P>,
+        level: u8,
+        path: u64,
+        s: &S,
+    ) -> Result<usize, InvariantError> {
+        let a = h as *const HAMT<K, V, P> as usize;
+
+        if let Some(&(l, p, n)) = self.hamts.get(&a) {
+            // Keys of a node can be placed only at one path.
+            return if (l, p) == (level, path) {
+                Ok(n)
+            } else {
+                Err(InvariantError::MisplacedKey)
+            };
+        }
+
+        let n = self.check_hamt(h, level, path, s)?;
+
+        if n < 2 {
+            return Err(InvariantError::SingletonHAMT);
+        }
+
+        self.hamts.insert(a, (level, path, n));
+
+        Ok(n)
+    }
+}
+
+fn check_key<K: Hash, S: BuildHasher>(
+    k: &K,
+    level: u8,
+    path: u64,
+    s: &S,
+) -> Result<(), InvariantError> {
+    let bits = 5 * (level as u32 + 1);
+    let mask = if bits < 64 { (1 << bits) - 1 } else { !0 };
+
+    if s.hash_one(k) & mask == path {
+        Ok(())
+    } else {
+        Err(InvariantError::MisplacedKey)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::bucket::Bucket;
+    use super::super::entry::Entry;
+    use super::super::hamt::{HAMT, MAX_LEVEL};
+    use super::InvariantError;
+    use crate::pointer::{ArcK, PointerKind};
+    use crate::HashMap;
+    use core::hash::{BuildHasher, Hash, Hasher};
+    use rand::random;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct CollidingKey(u8);
+
+    impl Hash for CollidingKey {
+        fn hash<H: Hasher>(&self, h: &mut H) {
+            0.hash(h)
+        }
+    }
+
+    fn from_entry(i: usize, e: Entry<usize, usize, ArcK>) -> HashMap<usize, usize> {
+        let mut es: [Entry<usize, usize, ArcK>; 32] = Default::default();
+        es[i] = e;
+
+        HashMap {
+            len: 1,
+            hamt: ArcK.pointer(HAMT::from_entries(es)),
+            hasher: Default::default(),
+            pointer_kind: ArcK,
+        }
+    }
+
+    // Nests an entry into HAMTs along the path of a key down to a level.
+    fn nest(k: usize, level: u8, i: usize, e: Entry<usize, usize, ArcK>) -> HashMap<usize, usize> {
+        let mut e = e;
+        let mut i = i;
+
+        for l in (0..level).rev() {
+            e = Entry::HAMT(from_entry(i, e).hamt);
+            i = index(k, l);
+        }
+
+        from_entry(i, e)
+    }
+
+    fn index(k: usize, level: u8) -> usize {
+        (HashMap::<usize, usize>::new().hasher().hash_one(k) >> (5 * level) & 0b11111) as usize
+    }
+
+    #[test]
+    fn check_invariants() {
+        let mut h: HashMap<i16, i16> = HashMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let k = random();
+
+            h = if random() {
+                h.insert(k, k)
+            } else {
+                h.remove(&k).unwrap_or(h)
+            };
+
+            assert_eq!(h.check_invariants(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn check_invariants_with_buckets() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..8 {
+            h = h.insert(CollidingKey(i), i);
+            assert_eq!(h.check_invariants(), Ok(()));
+        }
+
+        for i in 0..8 {
+            h = h.remove(&CollidingKey(i)).unwrap();
+            assert_eq!(h.check_invariants(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn length_mismatch() {
+        let mut h = HashMap::<_, _>::new().insert(0, 0);
+        h.len = 2;
+
+        assert_eq!(
+            h.check_invariants(),
+            Err(InvariantError::LengthMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn misplaced_key() {
+        assert_eq!(
+            from_entry((index(0, 0) + 1) % 32, Entry::KeyValue(0, 0)).check_invariants(),
+            Err(InvariantError::MisplacedKey)
+        );
+    }
+
+    #[test]
+    fn singleton_hamt() {
+        let h = from_entry(index(0, 1), Entry::KeyValue(0, 0));
+
+        assert_eq!(
+            from_entry(index(0, 0), Entry::HAMT(h.hamt)).check_invariants(),
+            Err(InvariantError::SingletonHAMT)
+        );
+    }
+
+    #[test]
+    fn misplaced_bucket() {
+        assert_eq!(
+            from_entry(
+                index(0, 0),
+                Entry::Bucket(ArcK.pointer(Bucket::new(0, 0, &ArcK)))
+            )
+            .check_invariants(),
+            Err(InvariantError::MisplacedBucket)
+        );
+    }
+
+    #[test]
+    fn singleton_bucket() {
+        let b = Entry::Bucket(ArcK.pointer(Bucket::new(0, 0, &ArcK)));
+
+        assert_eq!(
+            nest(0, MAX_LEVEL, index(0, MAX_LEVEL), b).check_invariants(),
+            Err(InvariantError::SingletonBucket)
+        );
+    }
+
+    #[test]
+    fn duplicate_key() {
+        let b = Entry::Bucket(ArcK.pointer(Bucket::from_key_values(vec![(0, 0), (0, 1)], &ArcK)));
+        let mut h = nest(0, MAX_LEVEL, index(0, MAX_LEVEL), b);
+        h.len = 2;
+
+        assert_eq!(h.check_invariants(), Err(InvariantError::DuplicateKey));
+    }
+
+    #[test]
+    fn misplaced_hamt() {
+        let h = from_entry(0, Entry::KeyValue(0, 0));
+
+        assert_eq!(
+            nest(0, MAX_LEVEL, index(0, MAX_LEVEL), Entry::HAMT(h.hamt)).check_invariants(),
+            Err(InvariantError::MisplacedHAMT)
+        );
+    }
+
+    #[test]
+    fn misplaced_key_beyond_hash() {
+        let i = index(0, MAX_LEVEL);
+
+        // Only the single key in sub-HAMTs is wrong here.
+        assert_eq!(
+            nest(0, MAX_LEVEL, i, Entry::KeyValue(0, 0)).check_invariants(),
+            Err(InvariantError::SingletonHAMT)
+        );
+        assert_eq!(
+            nest(0, MAX_LEVEL, i + 16, Entry::KeyValue(0, 0)).check_invariants(),
+            Err(InvariantError::MisplacedKey)
+        );
+    }
+}
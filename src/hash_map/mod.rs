@@ -1,53 +1,211 @@
+#[cfg(feature = "archive")]
+pub mod archive;
 mod bucket;
+mod builder;
+mod dot;
 mod entry;
 mod hamt;
 mod hashed_key;
+mod hasher;
+mod invariant;
 mod node;
-
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "std")]
+pub mod snapshot;
+mod stats;
+mod visitor;
+
+use crate::pointer::{ArcK, PointerKind};
+use alloc::string::String;
+pub(crate) use builder::Builder;
+use core::any::TypeId;
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{BuildHasher, Hash};
+use core::iter::FromIterator;
+pub(crate) use hamt::NUM_ENTRIES;
 use hamt::{HAMTIterator, HAMT};
 use hashed_key::HashedKey;
-use std::borrow::Borrow;
-use std::hash::Hash;
-use std::sync::Arc;
+pub use hasher::{DefaultBuildHasher, DefaultHasher};
+pub use invariant::InvariantError;
+pub use stats::Stats;
+use stats::{NodeSet, SharedBytes};
+pub use visitor::NodeVisitor;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct HashMap<K: Eq + Hash, V: PartialEq> {
+pub struct HashMap<K: Eq + Hash, V, S = DefaultBuildHasher, P: PointerKind = ArcK> {
     len: usize,
-    hamt: Arc<HAMT<K, V>>,
+    hamt: P::Pointer<HAMT<K, V, P>>,
+    hasher: S,
+    pointer_kind: P,
 }
 
-impl<K: Clone + Eq + Hash, V: Clone + PartialEq> HashMap<K, V> {
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, P: PointerKind + Default>
+    HashMap<K, V, DefaultBuildHasher, P>
+{
     pub fn new() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, S: BuildHasher, P: PointerKind + Default>
+    HashMap<K, V, S, P>
+{
+    pub fn with_hasher(s: S) -> Self {
+        Self::with_hasher_in(s, Default::default())
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, S: BuildHasher, P: PointerKind>
+    HashMap<K, V, S, P>
+{
+    /// Creates a map which allocates its nodes through a pointer kind.
+    pub fn with_hasher_in(s: S, p: P) -> Self {
         Self {
             len: 0,
-            hamt: HAMT::new().into(),
+            hamt: p.pointer(HAMT::new()),
+            hasher: s,
+            pointer_kind: p,
         }
     }
+}
 
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, S: BuildHasher + Clone, P: PointerKind>
+    HashMap<K, V, S, P>
+{
     pub fn insert(&self, k: K, v: V) -> Self {
-        let (h, b) = self.hamt.insert(HashedKey::new(k), v);
+        let (h, b) = self
+            .hamt
+            .insert(HashedKey::new(k, &self.hasher), v, &self.pointer_kind);
 
-        Self {
-            len: self.len + (b as usize),
-            hamt: h.into(),
-        }
+        self.with_hamt(self.len + (b as usize), h)
     }
 
     pub fn remove<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<Self>
     where
         K: Borrow<Q>,
     {
-        self.hamt.remove(HashedKey::new(k)).map(|h| Self {
-            len: self.len - 1,
-            hamt: h.into(),
-        })
+        self.hamt
+            .remove(HashedKey::new(k, &self.hasher), &self.pointer_kind)
+            .map(|h| self.with_hamt(self.len - 1, h))
+    }
+
+    /// Inserts, updates or removes a value of a key in a single traversal.
+    pub(crate) fn alter(&self, k: K, f: impl FnOnce(Option<&V>) -> Option<V>) -> Self {
+        let (h, d) = self
+            .hamt
+            .alter(HashedKey::new(k, &self.hasher), f, &self.pointer_kind);
+
+        self.with_hamt((self.len as isize + d) as usize, h)
+    }
+
+    /// Merges two maps node by node, combining values of keys in both by a
+    /// function.
+    ///
+    /// Keys in only one of the maps are kept for unions and dropped for
+    /// intersections. The function must return an equal value for equal values
+    /// on intersections as nodes shared by both maps are reused. It is called
+    /// for every key in both maps including ones in the shared nodes. Maps
+    /// with hashers other than the default one are merged key by key.
+    pub(crate) fn merge(
+        &self,
+        other: &Self,
+        union: bool,
+        mut f: impl FnMut(&V, &V) -> Option<V>,
+    ) -> Self
+    where
+        S: 'static,
+    {
+        if !Self::has_fixed_hasher() {
+            return self.merge_keys(other, union, f);
+        }
+
+        let (mut common, mut kept) = (0, 0);
+        let h = self.hamt.merge(
+            &other.hamt,
+            0,
+            union,
+            &mut |v, w| {
+                let x = f(v, w);
+                common += 1;
+                kept += x.is_some() as usize;
+                x
+            },
+            &self.hasher,
+            &self.pointer_kind,
+        );
+
+        let len = if union {
+            self.len + other.len - 2 * common + kept
+        } else {
+            kept
+        };
+
+        self.with_hamt(len, h)
     }
 
+    fn merge_keys(
+        &self,
+        other: &Self,
+        union: bool,
+        mut f: impl FnMut(&V, &V) -> Option<V>,
+    ) -> Self {
+        if union {
+            let mut m = self.clone();
+
+            for (k, w) in other {
+                m = m.alter(k.clone(), |v| match v {
+                    Some(v) => f(v, w),
+                    None => Some(w.clone()),
+                });
+            }
+
+            m
+        } else {
+            let mut m = Self::with_hasher_in(self.hasher.clone(), self.pointer_kind.clone());
+
+            for (k, v) in self {
+                if let Some(x) = other.get(k).and_then(|w| f(v, w)) {
+                    m = m.insert(k.clone(), x);
+                }
+            }
+
+            m
+        }
+    }
+
+    // Only builders of the default hasher are known to build the same hashers
+    // as builders do not implement `Eq` in general.
+    fn has_fixed_hasher() -> bool
+    where
+        S: 'static,
+    {
+        TypeId::of::<S>() == TypeId::of::<DefaultBuildHasher>()
+    }
+
+    fn with_hamt(&self, len: usize, h: HAMT<K, V, P>) -> Self {
+        Self {
+            len,
+            hamt: self.pointer_kind.pointer(h),
+            hasher: self.hasher.clone(),
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind> HashMap<K, V, S, P> {
     pub fn get<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
     {
-        self.hamt.get(HashedKey::new(k))
+        self.hamt.get(HashedKey::new(k, &self.hasher))
+    }
+
+    pub fn get_key_value<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        self.hamt.get_key_value(HashedKey::new(k, &self.hasher))
     }
 
     pub fn len(&self) -> usize {
@@ -57,19 +215,137 @@ impl<K: Clone + Eq + Hash, V: Clone + PartialEq> HashMap<K, V> {
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    pub fn pointer_kind(&self) -> &P {
+        &self.pointer_kind
+    }
+
+    /// Checks structural invariants of the internal trie.
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        invariant::check(&self.hamt, self.len, &self.hasher)
+    }
+
+    /// Visits every node and key-value pair in pre-order.
+    pub fn visit_nodes<N: NodeVisitor<K, V>>(&self, n: &mut N) {
+        self.hamt.visit_nodes(n)
+    }
+
+    /// Returns statistics of the internal structure.
+    pub fn stats(&self) -> Stats {
+        Stats::new(&self.hamt)
+    }
+
+    /// Returns the estimated number of bytes of nodes shared with another map.
+    pub fn shared_bytes_with(&self, other: &Self) -> usize {
+        let mut s = NodeSet::new();
+        self.visit_nodes(&mut s);
+
+        let mut b = SharedBytes::new(&s);
+        other.visit_nodes(&mut b);
+
+        b.bytes()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Eq + Hash, V: PartialEq, S, P: PointerKind> HashMap<K, V, S, P> {
+    /// Drops a map on a background thread.
+    pub fn drop_in_background(self) -> std::thread::JoinHandle<()>
+    where
+        Self: Send + 'static,
+    {
+        std::thread::spawn(move || drop(self))
+    }
+}
+
+impl<K: Debug + Eq + Hash, V: Debug + PartialEq, S, P: PointerKind> HashMap<K, V, S, P> {
+    /// Renders the internal structure in the DOT language of Graphviz.
+    pub fn to_dot(&self) -> String {
+        dot::to_dot(&[self])
+    }
+
+    /// Renders the internal structures of maps in the DOT language of Graphviz.
+    ///
+    /// Nodes shared among the maps are rendered only once.
+    pub fn to_dot_many(ms: &[&Self]) -> String {
+        dot::to_dot(ms)
+    }
 }
 
-impl<K: Clone + Eq + Hash, V: Clone + PartialEq> Default for HashMap<K, V> {
+impl<K: Eq + Hash, V: PartialEq, S: Clone, P: PointerKind> Clone for HashMap<K, V, S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            len: self.len,
+            hamt: self.hamt.clone(),
+            hasher: self.hasher.clone(),
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<K: Debug + Eq + Hash, V: Debug + PartialEq, S: Debug, P: PointerKind> Debug
+    for HashMap<K, V, S, P>
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("HashMap")
+            .field("len", &self.len)
+            .field("hamt", &*self.hamt)
+            .field("hasher", &self.hasher)
+            .finish()
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind> PartialEq for HashMap<K, V, S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        // Maps can have different structures if their hashers are different.
+        self.len == other.len
+            && (P::ptr_eq(&self.hamt, &other.hamt)
+                || *self.hamt == *other.hamt
+                || self.into_iter().all(|(k, v)| other.get(k) == Some(v)))
+    }
+}
+
+impl<K: Eq + Hash, V: Eq, S: BuildHasher, P: PointerKind> Eq for HashMap<K, V, S, P> {}
+
+impl<
+        K: Clone + Eq + Hash,
+        V: Clone + PartialEq,
+        S: BuildHasher + Default,
+        P: PointerKind + Default,
+    > Default for HashMap<K, V, S, P>
+{
     fn default() -> Self {
-        Self::new()
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<
+        K: Clone + Eq + Hash,
+        V: Clone + PartialEq,
+        S: BuildHasher + Default,
+        P: PointerKind + Default,
+    > FromIterator<(K, V)> for HashMap<K, V, S, P>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(i: I) -> Self {
+        let mut b = Builder::new(Default::default(), Default::default());
+
+        for (k, v) in i {
+            b.insert(k, v);
+        }
+
+        b.build()
     }
 }
 
-pub struct HashMapIterator<'a, K: 'a + Eq + Hash, V: 'a + PartialEq> {
-    hamt_iterator: HAMTIterator<'a, K, V>,
+pub struct HashMapIterator<'a, K: 'a + Eq + Hash, V: 'a, P: PointerKind = ArcK> {
+    hamt_iterator: HAMTIterator<'a, K, V, P>,
 }
 
-impl<'a, K: Eq + Hash, V: PartialEq> Iterator for HashMapIterator<'a, K, V> {
+impl<'a, K: Eq + Hash, V: PartialEq, P: PointerKind> Iterator for HashMapIterator<'a, K, V, P> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -77,8 +353,8 @@ impl<'a, K: Eq + Hash, V: PartialEq> Iterator for HashMapIterator<'a, K, V> {
     }
 }
 
-impl<'a, K: Eq + Hash, V: PartialEq> IntoIterator for &'a HashMap<K, V> {
-    type IntoIter = HashMapIterator<'a, K, V>;
+impl<'a, K: Eq + Hash, V: PartialEq, S, P: PointerKind> IntoIterator for &'a HashMap<K, V, S, P> {
+    type IntoIter = HashMapIterator<'a, K, V, P>;
     type Item = (&'a K, &'a V);
 
     fn into_iter(self) -> Self::IntoIter {
@@ -90,12 +366,11 @@ impl<'a, K: Eq + Hash, V: PartialEq> IntoIterator for &'a HashMap<K, V> {
 
 #[cfg(test)]
 mod test {
-    use super::HashMap;
-    use rand::{random, seq::SliceRandom, thread_rng};
+    use super::{DefaultBuildHasher, HashMap};
+    use crate::pointer::LeakK;
+    use std::collections::hash_map::RandomState;
+    use std::string::String;
     use std::thread::spawn;
-    use test::Bencher;
-
-    const NUM_ITERATIONS: usize = 1 << 12;
 
     #[test]
     fn new() {
@@ -103,115 +378,227 @@ mod test {
     }
 
     #[test]
-    fn insert() {
-        let h = HashMap::new();
+    fn with_hasher() {
+        let h: HashMap<_, _, _> = HashMap::with_hasher(RandomState::new());
+        let hh = HashMap::with_hasher(RandomState::new());
 
-        assert_eq!(h.len(), 0);
-        assert_eq!(h.insert(0, 0).len(), 1);
-        assert_eq!(h.insert(0, 0).insert(0, 0).len(), 1);
-        assert_eq!(h.insert(0, 0).insert(1, 0).len(), 2);
+        assert_eq!(h.insert(0, 0).get(&0), Some(&0));
+        assert_eq!(h.insert(0, 0).insert(1, 0), hh.insert(1, 0).insert(0, 0));
+        assert_ne!(h.insert(0, 0), hh.insert(0, 1));
+        assert_ne!(h.insert(0, 0), hh.insert(1, 0));
     }
 
     #[test]
-    fn insert_many_in_order() {
-        let mut h = HashMap::new();
+    fn send_and_sync() {
+        let m: HashMap<usize, usize> = HashMap::new();
+        spawn(move || m);
+        let m: HashMap<String, String> = HashMap::new();
+        spawn(move || m);
+    }
 
-        for i in 0..NUM_ITERATIONS {
-            h = h.insert(i, i);
-            assert_eq!(h.len(), i + 1);
-        }
+    #[test]
+    fn send_and_sync_leaked() {
+        let m: HashMap<usize, usize, DefaultBuildHasher, LeakK> = HashMap::default();
+        spawn(move || m);
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    fn insert_many_at_random() {
-        let mut h: HashMap<usize, usize> = HashMap::new();
+    fn drop_in_background() {
+        let mut h = HashMap::<_, _>::new();
 
-        for i in 0..NUM_ITERATIONS {
-            let k = random();
-            h = h.insert(k, k);
-            assert_eq!(h.len(), i + 1);
+        for i in 0..1000 {
+            h = h.insert(i, i);
         }
-    }
 
-    #[test]
-    fn remove() {
-        let h = HashMap::new();
+        let hh = h.insert(1000, 1000);
+
+        h.drop_in_background().join().unwrap();
 
-        assert_eq!(h.insert(0, 0).remove(&0), Some(h.clone()));
-        assert_eq!(h.insert(0, 0).remove(&1), None);
-        assert_eq!(h.insert(0, 0).insert(1, 0).remove(&0), Some(h.insert(1, 0)));
-        assert_eq!(h.insert(0, 0).insert(1, 0).remove(&1), Some(h.insert(0, 0)));
-        assert_eq!(h.insert(0, 0).insert(1, 0).remove(&2), None);
+        assert_eq!(hh.len(), 1001);
+        assert_eq!(hh.get(&0), Some(&0));
     }
 
-    #[test]
-    fn insert_remove_many() {
-        let mut h: HashMap<i16, i16> = HashMap::new();
+    macro_rules! test_pointer_kind {
+        ($name:ident, $pointer:ty) => {
+            mod $name {
+                use crate::hash_map::DefaultBuildHasher;
+                use rand::{random, seq::SliceRandom, thread_rng};
+                use std::collections::HashMap as StdHashMap;
+                use std::vec;
+                use std::vec::Vec;
 
-        for _ in 0..NUM_ITERATIONS {
-            let k = random();
-            let s = h.len();
-            let found = h.get(&k).is_some();
+                type HashMap<K, V> = crate::HashMap<K, V, DefaultBuildHasher, $pointer>;
 
-            if random() {
-                h = h.insert(k, k);
+                const NUM_ITERATIONS: usize = 1 << 12;
 
-                assert_eq!(h.len(), if found { s } else { s + 1 });
-                assert_eq!(h.get(&k), Some(&k));
-            } else {
-                h = h.remove(&k).unwrap_or(h);
+                #[test]
+                fn insert() {
+                    let h = HashMap::default();
 
-                assert_eq!(h.len(), if found { s - 1 } else { s });
-                assert_eq!(h.get(&k), None);
-            }
-        }
-    }
+                    assert_eq!(h.len(), 0);
+                    assert_eq!(h.insert(0, 0).len(), 1);
+                    assert_eq!(h.insert(0, 0).insert(0, 0).len(), 1);
+                    assert_eq!(h.insert(0, 0).insert(1, 0).len(), 2);
+                }
 
-    #[test]
-    fn get() {
-        let h = HashMap::new();
+                #[test]
+                fn insert_many_in_order() {
+                    let mut h = HashMap::default();
 
-        assert_eq!(h.insert(0, 0).get(&0), Some(&0));
-        assert_eq!(h.insert(0, 0).get(&1), None);
-        assert_eq!(h.insert(1, 0).get(&0), None);
-        assert_eq!(h.insert(1, 0).get(&1), Some(&0));
-        assert_eq!(h.insert(0, 0).insert(1, 0).get(&0), Some(&0));
-        assert_eq!(h.insert(0, 0).insert(1, 0).get(&1), Some(&0));
-        assert_eq!(h.insert(0, 0).insert(1, 0).get(&2), None);
-    }
+                    for i in 0..NUM_ITERATIONS {
+                        h = h.insert(i, i);
+                        assert_eq!(h.len(), i + 1);
+                    }
+                }
 
-    #[test]
-    fn equality() {
-        for _ in 0..8 {
-            let mut hs: [HashMap<i16, i16>; 2] = [HashMap::new(), HashMap::new()];
-            let mut is: Vec<i16> = (0..NUM_ITERATIONS).map(|_| random()).collect();
-            let mut ds: Vec<i16> = (0..NUM_ITERATIONS).map(|_| random()).collect();
-
-            for h in hs.iter_mut() {
-                is.shuffle(&mut thread_rng());
-                ds.shuffle(&mut thread_rng());
-
-                for i in &is {
-                    *h = h.insert(*i, *i);
+                #[test]
+                fn insert_many_at_random() {
+                    let mut h: HashMap<usize, usize> = HashMap::default();
+
+                    for i in 0..NUM_ITERATIONS {
+                        let k = random();
+                        h = h.insert(k, k);
+                        assert_eq!(h.len(), i + 1);
+                    }
                 }
 
-                for d in &ds {
-                    *h = h.remove(d).unwrap_or(h.clone());
+                #[test]
+                fn remove() {
+                    let h = HashMap::default();
+
+                    assert_eq!(h.insert(0, 0).remove(&0), Some(h.clone()));
+                    assert_eq!(h.insert(0, 0).remove(&1), None);
+                    assert_eq!(h.insert(0, 0).insert(1, 0).remove(&0), Some(h.insert(1, 0)));
+                    assert_eq!(h.insert(0, 0).insert(1, 0).remove(&1), Some(h.insert(0, 0)));
+                    assert_eq!(h.insert(0, 0).insert(1, 0).remove(&2), None);
                 }
-            }
 
-            assert_eq!(hs[0], hs[1]);
-        }
-    }
+                #[test]
+                fn insert_remove_many() {
+                    let mut h: HashMap<i16, i16> = HashMap::default();
 
-    #[test]
-    fn send_and_sync() {
-        let m: HashMap<usize, usize> = HashMap::new();
-        spawn(move || m);
-        let m: HashMap<String, String> = HashMap::new();
-        spawn(move || m);
+                    for _ in 0..NUM_ITERATIONS {
+                        let k = random();
+                        let s = h.len();
+                        let found = h.get(&k).is_some();
+
+                        if random() {
+                            h = h.insert(k, k);
+
+                            assert_eq!(h.len(), if found { s } else { s + 1 });
+                            assert_eq!(h.get(&k), Some(&k));
+                        } else {
+                            h = h.remove(&k).unwrap_or(h);
+
+                            assert_eq!(h.len(), if found { s - 1 } else { s });
+                            assert_eq!(h.get(&k), None);
+                        }
+                    }
+                }
+
+                #[test]
+                fn get() {
+                    let h = HashMap::default();
+
+                    assert_eq!(h.insert(0, 0).get(&0), Some(&0));
+                    assert_eq!(h.insert(0, 0).get(&1), None);
+                    assert_eq!(h.insert(1, 0).get(&0), None);
+                    assert_eq!(h.insert(1, 0).get(&1), Some(&0));
+                    assert_eq!(h.insert(0, 0).insert(1, 0).get(&0), Some(&0));
+                    assert_eq!(h.insert(0, 0).insert(1, 0).get(&1), Some(&0));
+                    assert_eq!(h.insert(0, 0).insert(1, 0).get(&2), None);
+                }
+
+                #[test]
+                fn get_key_value() {
+                    let h = HashMap::default().insert(0, 1);
+
+                    assert_eq!(h.get_key_value(&0), Some((&0, &1)));
+                    assert_eq!(h.get_key_value(&1), None);
+                }
+
+                #[test]
+                fn equality() {
+                    for _ in 0..8 {
+                        let mut hs: [HashMap<i16, i16>; 2] =
+                            [HashMap::default(), HashMap::default()];
+                        let mut is: Vec<i16> = (0..NUM_ITERATIONS).map(|_| random()).collect();
+                        let mut ds: Vec<i16> = (0..NUM_ITERATIONS).map(|_| random()).collect();
+
+                        for h in hs.iter_mut() {
+                            is.shuffle(&mut thread_rng());
+                            ds.shuffle(&mut thread_rng());
+
+                            for i in &is {
+                                *h = h.insert(*i, *i);
+                            }
+
+                            for d in &ds {
+                                *h = h.remove(d).unwrap_or(h.clone());
+                            }
+                        }
+
+                        assert_eq!(hs[0], hs[1]);
+                    }
+                }
+
+                #[test]
+                fn from_iterator() {
+                    let h = HashMap::default();
+
+                    assert_eq!(vec![].into_iter().collect::<HashMap<usize, usize>>(), h);
+                    assert_eq!(
+                        vec![(0, 0)].into_iter().collect::<HashMap<_, _>>(),
+                        h.insert(0, 0)
+                    );
+                    assert_eq!(
+                        vec![(0, 0), (1, 0), (0, 1)]
+                            .into_iter()
+                            .collect::<HashMap<_, _>>(),
+                        h.insert(1, 0).insert(0, 1)
+                    );
+                }
+
+                #[test]
+                fn std_hash_map() {
+                    let mut h: HashMap<i16, i16> = HashMap::default();
+                    let mut m = StdHashMap::new();
+
+                    for _ in 0..NUM_ITERATIONS {
+                        let k = random();
+
+                        if random() {
+                            h = h.insert(k, k);
+                            m.insert(k, k);
+                        } else {
+                            h = h.remove(&k).unwrap_or(h);
+                            m.remove(&k);
+                        }
+
+                        assert_eq!(h.len(), m.len());
+                        assert_eq!(h.get(&k), m.get(&k));
+                    }
+
+                    for (k, v) in &h {
+                        assert_eq!(m.get(k), Some(v));
+                    }
+                }
+            }
+        };
     }
 
+    test_pointer_kind!(arc, crate::pointer::ArcK);
+    test_pointer_kind!(rc, crate::pointer::RcK);
+    test_pointer_kind!(leak, crate::pointer::LeakK);
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod bench {
+    use super::HashMap;
+    use std::vec::Vec;
+    use test::Bencher;
+
     fn keys() -> Vec<i16> {
         (0..1000).collect()
     }
@@ -221,7 +608,7 @@ mod test {
         let ks = keys();
 
         b.iter(|| {
-            let mut h = HashMap::new();
+            let mut h = HashMap::<_, _>::new();
 
             for k in &ks {
                 h = h.insert(k, k);
@@ -232,7 +619,7 @@ mod test {
     #[bench]
     fn bench_get_1000(b: &mut Bencher) {
         let ks = keys();
-        let mut h = HashMap::new();
+        let mut h = HashMap::<_, _>::new();
 
         for k in &ks {
             h = h.insert(k, k);
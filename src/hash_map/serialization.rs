@@ -0,0 +1,131 @@
+use super::builder::Builder;
+use super::HashMap;
+use crate::pointer::PointerKind;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+impl<K: Eq + Hash + Serialize, V: PartialEq + Serialize, S, P: PointerKind> Serialize
+    for HashMap<K, V, S, P>
+{
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let mut m = serializer.serialize_map(Some(self.len))?;
+
+        for (k, v) in self {
+            m.serialize_entry(k, v)?;
+        }
+
+        m.end()
+    }
+}
+
+impl<'de, K, V, S, P> Deserialize<'de> for HashMap<K, V, S, P>
+where
+    K: Clone + Eq + Hash + Deserialize<'de>,
+    V: Clone + PartialEq + Deserialize<'de>,
+    S: BuildHasher + Default,
+    P: PointerKind + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(HashMapVisitor(PhantomData))
+    }
+}
+
+struct HashMapVisitor<K, V, S, P>(PhantomData<(K, V, S, P)>);
+
+impl<'de, K, V, S, P> Visitor<'de> for HashMapVisitor<K, V, S, P>
+where
+    K: Clone + Eq + Hash + Deserialize<'de>,
+    V: Clone + PartialEq + Deserialize<'de>,
+    S: BuildHasher + Default,
+    P: PointerKind + Default,
+{
+    type Value = HashMap<K, V, S, P>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut a: A) -> Result<Self::Value, A::Error> {
+        let mut b = Builder::new(Default::default(), Default::default());
+
+        while let Some((k, v)) = a.next_entry()? {
+            b.insert(k, v);
+        }
+
+        Ok(b.build())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use std::hash::{Hash, Hasher};
+
+    #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+    struct CollidingKey(u8);
+
+    impl Hash for CollidingKey {
+        fn hash<H: Hasher>(&self, h: &mut H) {
+            0.hash(h)
+        }
+    }
+
+    macro_rules! test_pointer_kind {
+        ($name:ident, $pointer:ty) => {
+            mod $name {
+                use super::CollidingKey;
+                use crate::hash_map::DefaultBuildHasher;
+                use rand::random;
+
+                type HashMap<K, V> = crate::HashMap<K, V, DefaultBuildHasher, $pointer>;
+
+                const NUM_ITERATIONS: usize = 1 << 12;
+
+                #[test]
+                fn serialize() {
+                    assert_eq!(
+                        serde_json::to_string(&HashMap::default().insert(0, 1)).unwrap(),
+                        r#"{"0":1}"#
+                    );
+                }
+
+                #[test]
+                fn round_trip() {
+                    let mut h: HashMap<i16, i16> = HashMap::default();
+
+                    for _ in 0..NUM_ITERATIONS {
+                        let k = random();
+                        h = h.insert(k, k);
+                    }
+
+                    let s = serde_json::to_string(&h).unwrap();
+
+                    assert_eq!(serde_json::from_str::<HashMap<i16, i16>>(&s).unwrap(), h);
+                }
+
+                #[test]
+                fn round_trip_with_buckets() {
+                    let mut h = HashMap::default();
+
+                    for k in 0..42 {
+                        h = h.insert(CollidingKey(k), k);
+                    }
+
+                    let s = serde_json::to_string(&h).unwrap();
+
+                    assert_eq!(
+                        serde_json::from_str::<HashMap<CollidingKey, u8>>(&s).unwrap(),
+                        h
+                    );
+                }
+            }
+        };
+    }
+
+    test_pointer_kind!(arc, crate::pointer::ArcK);
+    test_pointer_kind!(rc, crate::pointer::RcK);
+    test_pointer_kind!(leak, crate::pointer::LeakK);
+}
@@ -0,0 +1,728 @@
+//! A binary format which stores many versions of hash maps sharing their nodes.
+//!
+//! Every distinct node is written only once and referred to by its ID from its
+//! parents and map roots, so the size of a snapshot is proportional to the
+//! number of unique nodes rather than the total size of maps in it.
+
+use super::bucket::Bucket;
+use super::entry::Entry;
+use super::hamt::{HAMT, NUM_ENTRIES};
+use super::invariant::Checker;
+use super::{DefaultBuildHasher, HashMap};
+use crate::pointer::{ArcK, PointerKind};
+use core::convert::TryFrom;
+use core::hash::{BuildHasher, Hash};
+use std::boxed::Box;
+use std::collections::HashMap as StdHashMap;
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::string::String;
+use std::vec::Vec;
+
+const MAGIC: &[u8; 4] = b"IMLK";
+const VERSION: u8 = 1;
+
+const HAMT_TAG: u8 = 0;
+const BUCKET_TAG: u8 = 1;
+
+const KEY_VALUE_TAG: u8 = 0;
+const NODE_TAG: u8 = 1;
+
+/// A type which can be written into snapshots.
+pub trait Encode {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()>;
+}
+
+/// A type which can be read from snapshots.
+pub trait Decode: Sized {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self>;
+}
+
+/// Writes hash maps into a snapshot.
+pub fn write<W, K, V, S, P>(w: &mut W, ms: &[&HashMap<K, V, S, P>]) -> io::Result<()>
+where
+    W: Write,
+    K: Eq + Hash + Encode,
+    V: PartialEq + Encode,
+    P: PointerKind,
+{
+    let mut ns = Nodes::default();
+
+    for m in ms {
+        ns.collect_hamt(&m.hamt);
+    }
+
+    w.write_all(MAGIC)?;
+    VERSION.encode(w)?;
+    write_size(w, ns.nodes.len())?;
+
+    for n in &ns.nodes {
+        match n {
+            NodeRef::HAMT(h) => {
+                HAMT_TAG.encode(w)?;
+
+                let mut bs = 0u32;
+
+                for (i, e) in h.entries().iter().enumerate() {
+                    if !matches!(e, Entry::Empty) {
+                        bs |= 1 << i;
+                    }
+                }
+
+                bs.encode(w)?;
+
+                for e in h.entries().iter() {
+                    match e {
+                        Entry::Empty => {}
+                        Entry::KeyValue(k, v) => {
+                            KEY_VALUE_TAG.encode(w)?;
+                            k.encode(w)?;
+                            v.encode(w)?;
+                        }
+                        Entry::HAMT(h) => {
+                            NODE_TAG.encode(w)?;
+                            write_size(w, ns.id(&**h))?;
+                        }
+                        Entry::Bucket(b) => {
+                            NODE_TAG.encode(w)?;
+                            write_size(w, ns.id(&**b))?;
+                        }
+                    }
+                }
+            }
+            NodeRef::Bucket(b) => {
+                BUCKET_TAG.encode(w)?;
+                write_size(w, b.len())?;
+
+                for (k, v) in *b {
+                    k.encode(w)?;
+                    v.encode(w)?;
+                }
+            }
+        }
+    }
+
+    write_size(w, ms.len())?;
+
+    for m in ms {
+        write_size(w, m.len)?;
+        write_size(w, ns.id(&*m.hamt))?;
+    }
+
+    Ok(())
+}
+
+/// Reads hash maps from a snapshot restoring their shared nodes.
+pub fn read<R: Read, K: Clone + Eq + Hash + Decode, V: Clone + PartialEq + Decode>(
+    r: &mut R,
+) -> io::Result<Vec<HashMap<K, V>>> {
+    read_with_hasher::<_, _, _, _, ArcK>(r, &DefaultBuildHasher::default())
+}
+
+/// Reads hash maps from a snapshot with a hasher builder.
+///
+/// The hasher builder must hash keys in the same way as the ones of the
+/// original maps.
+pub fn read_with_hasher<R, K, V, S, P>(r: &mut R, s: &S) -> io::Result<Vec<HashMap<K, V, S, P>>>
+where
+    R: Read,
+    K: Clone + Eq + Hash + Decode,
+    V: Clone + PartialEq + Decode,
+    S: BuildHasher + Clone,
+    P: PointerKind + Default,
+{
+    read_with_hasher_in(r, s, &Default::default())
+}
+
+/// Reads hash maps from a snapshot with a hasher builder and a pointer kind
+/// through which their nodes are allocated.
+pub fn read_with_hasher_in<R, K, V, S, P>(
+    r: &mut R,
+    s: &S,
+    p: &P,
+) -> io::Result<Vec<HashMap<K, V, S, P>>>
+where
+    R: Read,
+    K: Clone + Eq + Hash + Decode,
+    V: Clone + PartialEq + Decode,
+    S: BuildHasher + Clone,
+    P: PointerKind,
+{
+    let mut m = [0; 4];
+    r.read_exact(&mut m)?;
+
+    if &m != MAGIC {
+        return Err(invalid_data("invalid magic number"));
+    } else if u8::decode(r)? != VERSION {
+        return Err(invalid_data("unsupported version"));
+    }
+
+    read_maps(r, s, p).map_err(|e| {
+        if e.kind() == ErrorKind::UnexpectedEof {
+            invalid_data("truncated snapshot")
+        } else {
+            e
+        }
+    })
+}
+
+fn read_maps<R, K, V, S, P>(r: &mut R, s: &S, p: &P) -> io::Result<Vec<HashMap<K, V, S, P>>>
+where
+    R: Read,
+    K: Clone + Eq + Hash + Decode,
+    V: Clone + PartialEq + Decode,
+    S: BuildHasher + Clone,
+    P: PointerKind,
+{
+    // Vectors are not preallocated as sizes are not trusted.
+    let mut ns: Vec<Node<K, V, P>> = vec![];
+
+    for _ in 0..read_size(r)? {
+        ns.push(match u8::decode(r)? {
+            HAMT_TAG => {
+                let bs = u32::decode(r)?;
+                let mut es: [Entry<K, V, P>; NUM_ENTRIES] = Default::default();
+
+                for (i, e) in es.iter_mut().enumerate() {
+                    if bs & (1 << i) == 0 {
+                        continue;
+                    }
+
+                    *e = match u8::decode(r)? {
+                        KEY_VALUE_TAG => Entry::KeyValue(K::decode(r)?, V::decode(r)?),
+                        NODE_TAG => match ns.get(read_size(r)?) {
+                            Some(Node::HAMT(h)) => Entry::HAMT(h.clone()),
+                            Some(Node::Bucket(b)) => Entry::Bucket(b.clone()),
+                            None => return Err(invalid_data("invalid node ID")),
+                        },
+                        _ => return Err(invalid_data("invalid entry tag")),
+                    };
+                }
+
+                Node::HAMT(p.pointer(HAMT::from_entries(es)))
+            }
+            BUCKET_TAG => {
+                let n = read_size(r)?;
+
+                if n == 0 {
+                    return Err(invalid_data("empty bucket"));
+                }
+
+                let mut kvs: Vec<(K, V)> = vec![];
+
+                for _ in 0..n {
+                    let k = K::decode(r)?;
+
+                    if kvs.iter().any(|(kk, _)| kk == &k) {
+                        return Err(invalid_data("duplicate key in bucket"));
+                    }
+
+                    kvs.push((k, V::decode(r)?));
+                }
+
+                Node::Bucket(p.pointer(Bucket::from_key_values(kvs, p)))
+            }
+            _ => return Err(invalid_data("invalid node tag")),
+        });
+    }
+
+    let mut ms = vec![];
+    let mut c = Checker::default();
+
+    for _ in 0..read_size(r)? {
+        let len = read_size(r)?;
+
+        ms.push(match ns.get(read_size(r)?) {
+            Some(Node::HAMT(h)) => {
+                c.check(h, len, s)
+                    .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+                HashMap {
+                    len,
+                    hamt: h.clone(),
+                    hasher: s.clone(),
+                    pointer_kind: p.clone(),
+                }
+            }
+            _ => return Err(invalid_data("invalid root node ID")),
+        });
+    }
+
+    Ok(ms)
+}
+
+enum Node<K: Eq + Hash, V: PartialEq, P: PointerKind> {
+    HAMT(P::Pointer<HAMT<K, V, P>>),
+    Bucket(P::Pointer<Bucket<K, V, P>>),
+}
+
+enum NodeRef<'a, K: Eq + Hash, V: PartialEq, P: PointerKind> {
+    HAMT(&'a HAMT<K, V, P>),
+    Bucket(&'a Bucket<K, V, P>),
+}
+
+// Nodes in post order keyed by their addresses.
+struct Nodes<'a, K: Eq + Hash, V: PartialEq, P: PointerKind> {
+    nodes: Vec<NodeRef<'a, K, V, P>>,
+    ids: StdHashMap<*const (), usize>,
+}
+
+impl<'a, K: Eq + Hash, V: PartialEq, P: PointerKind> Nodes<'a, K, V, P> {
+    fn collect_hamt(&mut self, h: &'a HAMT<K, V, P>) {
+        if self.ids.contains_key(&address(h)) {
+            return;
+        }
+
+        for e in h.entries().iter() {
+            match e {
+                Entry::HAMT(h) => self.collect_hamt(h),
+                Entry::Bucket(b) if !self.ids.contains_key(&address(&**b)) => {
+                    self.insert(address(&**b), NodeRef::Bucket(b))
+                }
+                _ => {}
+            }
+        }
+
+        self.insert(address(h), NodeRef::HAMT(h));
+    }
+
+    fn insert(&mut self, a: *const (), n: NodeRef<'a, K, V, P>) {
+        self.ids.insert(a, self.nodes.len());
+        self.nodes.push(n);
+    }
+
+    fn id<T>(&self, n: &T) -> usize {
+        self.ids[&address(n)]
+    }
+}
+
+impl<'a, K: Eq + Hash, V: PartialEq, P: PointerKind> Default for Nodes<'a, K, V, P> {
+    fn default() -> Self {
+        Self {
+            nodes: vec![],
+            ids: StdHashMap::new(),
+        }
+    }
+}
+
+fn address<T>(x: &T) -> *const () {
+    x as *const T as *const ()
+}
+
+fn write_size<W: Write>(w: &mut W, mut n: usize) -> io::Result<()> {
+    loop {
+        let b = (n & 0x7f) as u8;
+        n >>= 7;
+
+        if n == 0 {
+            return b.encode(w);
+        }
+
+        (b | 0x80).encode(w)?;
+    }
+}
+
+fn read_size<R: Read>(r: &mut R) -> io::Result<usize> {
+    let mut n = 0u64;
+
+    for s in (0..64).step_by(7) {
+        let b = u8::decode(r)?;
+        let x = (b & 0x7f) as u64;
+
+        // The last byte has only one bit left in 64 bits.
+        if x << s >> s != x {
+            break;
+        }
+
+        n |= x << s;
+
+        if b & 0x80 == 0 {
+            return usize::try_from(n).map_err(|_| invalid_data("too large size"));
+        }
+    }
+
+    Err(invalid_data("too large size"))
+}
+
+fn invalid_data(s: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, s)
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                    w.write_all(&self.to_le_bytes())
+                }
+            }
+
+            impl Decode for $t {
+                fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+                    let mut bs = [0; core::mem::size_of::<$t>()];
+                    r.read_exact(&mut bs)?;
+                    Ok(Self::from_le_bytes(bs))
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Encode for usize {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write_size(w, *self)
+    }
+}
+
+impl Decode for usize {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        read_size(r)
+    }
+}
+
+impl Encode for isize {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as i64).encode(w)
+    }
+}
+
+impl Decode for isize {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        Ok(i64::decode(r)? as isize)
+    }
+}
+
+impl Encode for bool {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u8).encode(w)
+    }
+}
+
+impl Decode for bool {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        match u8::decode(r)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid boolean")),
+        }
+    }
+}
+
+impl Encode for char {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u32).encode(w)
+    }
+}
+
+impl Decode for char {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        core::char::from_u32(u32::decode(r)?).ok_or_else(|| invalid_data("invalid character"))
+    }
+}
+
+impl Encode for () {
+    fn encode<W: Write>(&self, _: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Decode for () {
+    fn decode<R: Read>(_: &mut R) -> io::Result<Self> {
+        Ok(())
+    }
+}
+
+impl Encode for str {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write_size(w, self.len())?;
+        w.write_all(self.as_bytes())
+    }
+}
+
+impl Encode for String {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.as_str().encode(w)
+    }
+}
+
+impl Decode for String {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        String::from_utf8(Vec::decode(r)?).map_err(|_| invalid_data("invalid UTF-8 string"))
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write_size(w, self.len())?;
+
+        for x in self {
+            x.encode(w)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.as_slice().encode(w)
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut xs = vec![];
+
+        for _ in 0..read_size(r)? {
+            xs.push(T::decode(r)?);
+        }
+
+        Ok(xs)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            None => false.encode(w),
+            Some(x) => {
+                true.encode(w)?;
+                x.encode(w)
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        Ok(if bool::decode(r)? {
+            Some(T::decode(r)?)
+        } else {
+            None
+        })
+    }
+}
+
+impl<T: ?Sized + Encode> Encode for &T {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (**self).encode(w)
+    }
+}
+
+impl<T: ?Sized + Encode> Encode for Box<T> {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (**self).encode(w)
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        Ok(Box::new(T::decode(r)?))
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.0.encode(w)?;
+        self.1.encode(w)
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+        Ok((A::decode(r)?, B::decode(r)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::entry::Entry;
+    use super::super::hamt::{HAMT, NUM_ENTRIES};
+    use super::super::{DefaultBuildHasher, HashMap};
+    use super::{read, read_size, read_with_hasher, write, write_size, Decode, Encode};
+    use crate::pointer::{ArcK, LeakK, PointerKind};
+    use rand::random;
+    use std::hash::{BuildHasher, Hash, Hasher};
+    use std::io::{self, ErrorKind, Read, Write};
+    use std::string::{String, ToString};
+    use std::vec;
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct CollidingKey(u8);
+
+    impl Hash for CollidingKey {
+        fn hash<H: Hasher>(&self, h: &mut H) {
+            0.hash(h)
+        }
+    }
+
+    impl Encode for CollidingKey {
+        fn encode<W: Write>(&self, w: &mut W) -> io::Result<()> {
+            self.0.encode(w)
+        }
+    }
+
+    impl Decode for CollidingKey {
+        fn decode<R: Read>(r: &mut R) -> io::Result<Self> {
+            Ok(Self(u8::decode(r)?))
+        }
+    }
+
+    fn random_map() -> HashMap<i16, i16> {
+        let mut h = HashMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let k = random();
+            h = h.insert(k, k);
+        }
+
+        h
+    }
+
+    fn write_to_vec<K: Eq + Hash + Encode, V: PartialEq + Encode>(
+        ms: &[&HashMap<K, V>],
+    ) -> Vec<u8> {
+        let mut bs = vec![];
+        write(&mut bs, ms).unwrap();
+        bs
+    }
+
+    #[test]
+    fn write_empty() {
+        write_to_vec::<usize, usize>(&[]);
+        write_to_vec::<usize, usize>(&[&HashMap::new()]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let h = random_map();
+        let hh = h.insert(42, 0);
+
+        assert_eq!(
+            read::<_, i16, i16>(&mut write_to_vec(&[&h, &hh]).as_slice()).unwrap(),
+            vec![h, hh]
+        );
+    }
+
+    #[test]
+    fn round_trip_with_buckets() {
+        let mut h = HashMap::new();
+
+        for k in 0..42 {
+            h = h.insert(CollidingKey(k), vec![k.to_string()]);
+        }
+
+        assert_eq!(
+            read::<_, CollidingKey, Vec<String>>(&mut write_to_vec(&[&h]).as_slice()).unwrap(),
+            vec![h]
+        );
+    }
+
+    #[test]
+    fn share_nodes() {
+        let h = random_map();
+        let hh = h.insert(42, 0);
+
+        let one = write_to_vec(&[&h]).len();
+        let two = write_to_vec(&[&h, &hh]).len();
+
+        assert!(two < one + one / 8);
+        assert!(write_to_vec(&[&h, &h]).len() < one + 8);
+
+        let ms = read::<_, i16, i16>(&mut write_to_vec(&[&h, &h]).as_slice()).unwrap();
+
+        assert!(ArcK::ptr_eq(&ms[0].hamt, &ms[1].hamt));
+    }
+
+    #[test]
+    fn round_trip_leak() {
+        let h = random_map()
+            .into_iter()
+            .map(|(k, v)| (*k, *v))
+            .collect::<HashMap<_, _, DefaultBuildHasher, LeakK>>();
+        let mut bs = vec![];
+
+        write(&mut bs, &[&h]).unwrap();
+
+        assert_eq!(
+            read_with_hasher::<_, i16, i16, _, LeakK>(&mut bs.as_slice(), h.hasher()).unwrap(),
+            vec![h]
+        );
+    }
+
+    #[test]
+    fn read_invalid_data() {
+        assert!(read::<_, usize, usize>(&mut b"".as_ref()).is_err());
+        assert!(read::<_, usize, usize>(&mut b"IMLK\x02".as_ref()).is_err());
+        assert!(read::<_, usize, usize>(&mut b"IMLK\x01\x01\x02".as_ref()).is_err());
+        assert!(read::<_, usize, usize>(&mut b"IMLK\x01\x00\x01\x00\x00".as_ref()).is_err());
+        assert_eq!(
+            read::<_, u8, u8>(&mut b"IMLK\x01\xff\xff\xff\xff\xff\xff\xff\xff\x7f".as_ref())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidData
+        );
+        assert_eq!(
+            Vec::<u8>::decode(&mut b"\xff\xff\xff\xff\xff\xff\xff\xff\x7f".as_ref())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn read_sizes() {
+        for n in [0, 1, 127, 128, 1 << 32, usize::MAX] {
+            let mut bs = vec![];
+            write_size(&mut bs, n).unwrap();
+
+            assert_eq!(read_size(&mut bs.as_slice()).unwrap(), n);
+        }
+
+        for bs in [
+            b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x02".as_ref(),
+            b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x81\x00".as_ref(),
+        ] {
+            assert_eq!(
+                read_size(&mut &*bs).unwrap_err().kind(),
+                ErrorKind::InvalidData
+            );
+        }
+    }
+
+    #[test]
+    fn read_invalid_map() {
+        let mut h = HashMap::<usize, usize>::new().insert(0, 0);
+        h.len = 2;
+
+        assert_eq!(
+            read::<_, usize, usize>(&mut write_to_vec(&[&h]).as_slice())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidData
+        );
+
+        let mut es: [Entry<usize, usize, ArcK>; NUM_ENTRIES] = Default::default();
+        es[0] = Entry::KeyValue(0, 0);
+        let mut ees: [Entry<usize, usize, ArcK>; NUM_ENTRIES] = Default::default();
+        ees[(h.hasher().hash_one(0) & 0b11111) as usize] =
+            Entry::HAMT(ArcK.pointer(HAMT::from_entries(es)));
+        let h = HashMap {
+            len: 1,
+            hamt: ArcK.pointer(HAMT::from_entries(ees)),
+            hasher: h.hasher,
+            pointer_kind: ArcK,
+        };
+
+        assert_eq!(
+            read::<_, usize, usize>(&mut write_to_vec(&[&h]).as_slice())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use super::bucket::Bucket;
+use super::entry::Entry;
+use super::hamt::HAMT;
+use super::visitor::NodeVisitor;
+use crate::pointer::PointerKind;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::hash::Hash;
+use core::mem;
+
+/// Statistics of the structure of a hash map.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Numbers of HAMT nodes at each level.
+    pub hamt_nodes: Vec<usize>,
+    /// Average number of occupied entries in HAMT nodes.
+    pub average_fill: f64,
+    /// Maximum number of occupied entries in HAMT nodes.
+    pub max_fill: usize,
+    /// Number of collision buckets.
+    pub buckets: usize,
+    /// Total number of key-value pairs in collision buckets.
+    pub bucket_entries: usize,
+    /// Maximum number of key-value pairs in collision buckets.
+    pub max_bucket_len: usize,
+    /// Number of levels of HAMT nodes.
+    pub depth: usize,
+    /// Estimated number of bytes of nodes on heap.
+    pub heap_bytes: usize,
+}
+
+impl Stats {
+    pub(super) fn new<K: Eq + Hash, V: PartialEq, P: PointerKind>(h: &HAMT<K, V, P>) -> Self {
+        let mut s = Self::default();
+        let mut fill = 0;
+
+        s.collect(h, 0, &mut fill);
+
+        s.depth = s.hamt_nodes.len();
+        s.average_fill = fill as f64 / s.hamt_nodes.iter().sum::<usize>() as f64;
+
+        s
+    }
+
+    fn collect<K: Eq + Hash, V: PartialEq, P: PointerKind>(
+        &mut self,
+        h: &HAMT<K, V, P>,
+        level: usize,
+        fill: &mut usize,
+    ) {
+        if self.hamt_nodes.len() == level {
+            self.hamt_nodes.push(0);
+        }
+
+        self.hamt_nodes[level] += 1;
+        self.heap_bytes += mem::size_of::<HAMT<K, V, P>>();
+
+        let mut n = 0;
+
+        for e in h.entries() {
+            match e {
+                Entry::Empty => continue,
+                Entry::KeyValue(_, _) => {}
+                Entry::HAMT(h) => self.collect(h, level + 1, fill),
+                Entry::Bucket(b) => self.collect_bucket(b),
+            }
+
+            n += 1;
+        }
+
+        *fill += n;
+        self.max_fill = self.max_fill.max(n);
+    }
+
+    fn collect_bucket<K: Eq + Hash, V: PartialEq, P: PointerKind>(&mut self, b: &Bucket<K, V, P>) {
+        self.buckets += 1;
+        self.bucket_entries += b.len();
+        self.max_bucket_len = self.max_bucket_len.max(b.len());
+        self.heap_bytes += mem::size_of::<Bucket<K, V, P>>() + b.len() * mem::size_of::<(K, V)>();
+    }
+}
+
+pub(super) struct NodeSet {
+    nodes: BTreeSet<*const u8>,
+}
+
+impl NodeSet {
+    pub fn new() -> Self {
+        Self {
+            nodes: BTreeSet::new(),
+        }
+    }
+}
+
+impl<K, V> NodeVisitor<K, V> for NodeSet {
+    fn visit_hamt(&mut self, address: *const u8, _: usize) -> bool {
+        self.nodes.insert(address)
+    }
+
+    fn visit_bucket(&mut self, address: *const u8, _: usize) -> bool {
+        self.nodes.insert(address)
+    }
+
+    fn visit_buffer(&mut self, address: *const u8, _: usize) {
+        self.nodes.insert(address);
+    }
+}
+
+pub(super) struct SharedBytes<'a> {
+    nodes: &'a NodeSet,
+    bytes: usize,
+}
+
+impl<'a> SharedBytes<'a> {
+    pub fn new(nodes: &'a NodeSet) -> Self {
+        Self { nodes, bytes: 0 }
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    fn visit_node(&mut self, address: *const u8, size: usize) -> bool {
+        if self.nodes.nodes.contains(&address) {
+            self.bytes += size;
+        }
+
+        true
+    }
+}
+
+impl<'a, K, V> NodeVisitor<K, V> for SharedBytes<'a> {
+    fn visit_hamt(&mut self, address: *const u8, size: usize) -> bool {
+        self.visit_node(address, size)
+    }
+
+    fn visit_bucket(&mut self, address: *const u8, size: usize) -> bool {
+        self.visit_node(address, size)
+    }
+
+    fn visit_buffer(&mut self, address: *const u8, size: usize) {
+        self.visit_node(address, size);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::HashMap;
+    use core::hash::{Hash, Hasher};
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct CollidingKey(u8);
+
+    impl Hash for CollidingKey {
+        fn hash<H: Hasher>(&self, h: &mut H) {
+            0.hash(h)
+        }
+    }
+
+    #[test]
+    fn stats_empty() {
+        let s = HashMap::<usize, usize>::new().stats();
+
+        assert_eq!(s.hamt_nodes, vec![1]);
+        assert_eq!(s.average_fill, 0.0);
+        assert_eq!(s.max_fill, 0);
+        assert_eq!(s.buckets, 0);
+        assert_eq!(s.depth, 1);
+        assert!(s.heap_bytes > 0);
+    }
+
+    #[test]
+    fn stats() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..1000 {
+            h = h.insert(i, i);
+        }
+
+        let s = h.stats();
+
+        assert_eq!(s.hamt_nodes[0], 1);
+        assert!(s.depth > 1);
+        assert_eq!(s.depth, s.hamt_nodes.len());
+        assert!(s.average_fill > 1.0);
+        assert!(s.max_fill <= 32);
+        assert_eq!(s.buckets, 0);
+    }
+
+    #[test]
+    fn stats_with_buckets() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..8 {
+            h = h.insert(CollidingKey(i), i);
+        }
+
+        let s = h.stats();
+
+        assert_eq!(s.depth, 13);
+        assert_eq!(s.buckets, 1);
+        assert_eq!(s.bucket_entries, 8);
+        assert_eq!(s.max_bucket_len, 8);
+    }
+
+    #[test]
+    fn shared_bytes_with() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..1000 {
+            h = h.insert(i, i);
+        }
+
+        let bytes = h.stats().heap_bytes;
+        let hh = h.insert(1000, 1000);
+
+        assert_eq!(h.shared_bytes_with(&h), bytes);
+        assert!(h.shared_bytes_with(&hh) > 0);
+        assert!(h.shared_bytes_with(&hh) < bytes);
+        assert_eq!(h.shared_bytes_with(&HashMap::new()), 0);
+    }
+
+    #[test]
+    fn shared_bytes_with_buckets() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..8 {
+            h = h.insert(CollidingKey(i), i);
+        }
+
+        assert_eq!(h.shared_bytes_with(&h.clone()), h.stats().heap_bytes);
+    }
+}
@@ -0,0 +1,167 @@
+/// A visitor of nodes and key-value pairs in hash maps.
+///
+/// Addresses point to nodes themselves rather than to headers of their
+/// pointers, such as reference counts.
+pub trait NodeVisitor<K, V> {
+    /// Visits a HAMT node. Its children are skipped if it returns `false`.
+    fn visit_hamt(&mut self, _address: *const u8, _size: usize) -> bool {
+        true
+    }
+
+    /// Visits a bucket node. Its children are skipped if it returns `false`.
+    fn visit_bucket(&mut self, _address: *const u8, _size: usize) -> bool {
+        true
+    }
+
+    /// Visits a buffer of key-value pairs owned by a bucket node.
+    fn visit_buffer(&mut self, _address: *const u8, _size: usize) {}
+
+    /// Visits a key-value pair.
+    fn visit_key_value(&mut self, _key: &K, _value: &V) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::NodeVisitor;
+    use crate::hash_map::DefaultBuildHasher;
+    use crate::pointer::{AllocK, Global, NodeAllocator};
+    use crate::HashMap;
+    use core::alloc::Layout;
+    use core::cell::RefCell;
+    use core::hash::{Hash, Hasher};
+    use core::ptr::NonNull;
+    use std::collections::BTreeSet;
+    use std::vec::Vec;
+
+    #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+    struct CollidingKey(u8);
+
+    impl Hash for CollidingKey {
+        fn hash<H: Hasher>(&self, h: &mut H) {
+            0.hash(h)
+        }
+    }
+
+    struct Visitor<K> {
+        hamts: BTreeSet<*const u8>,
+        buckets: BTreeSet<*const u8>,
+        buffers: BTreeSet<*const u8>,
+        keys: Vec<K>,
+        skip: bool,
+    }
+
+    impl<K> Visitor<K> {
+        fn new(skip: bool) -> Self {
+            Self {
+                hamts: BTreeSet::new(),
+                buckets: BTreeSet::new(),
+                buffers: BTreeSet::new(),
+                keys: Vec::new(),
+                skip,
+            }
+        }
+    }
+
+    impl<K: Clone, V> NodeVisitor<K, V> for Visitor<K> {
+        fn visit_hamt(&mut self, address: *const u8, size: usize) -> bool {
+            assert!(size > 0);
+            assert!(self.hamts.insert(address));
+            !self.skip
+        }
+
+        fn visit_bucket(&mut self, address: *const u8, size: usize) -> bool {
+            assert!(size > 0);
+            assert!(self.buckets.insert(address));
+            !self.skip
+        }
+
+        fn visit_buffer(&mut self, address: *const u8, size: usize) {
+            assert!(size > 0);
+            assert!(self.buffers.insert(address));
+        }
+
+        fn visit_key_value(&mut self, k: &K, _: &V) {
+            self.keys.push(k.clone());
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct RecordingAllocator<'a>(&'a RefCell<BTreeSet<*const u8>>);
+
+    unsafe impl NodeAllocator for RecordingAllocator<'_> {
+        fn allocate(&self, layout: Layout) -> NonNull<u8> {
+            let p = Global.allocate(layout);
+            self.0.borrow_mut().insert(p.as_ptr());
+            p
+        }
+    }
+
+    #[test]
+    fn visit_nodes() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..1000 {
+            h = h.insert(i, i);
+        }
+
+        let mut v = Visitor::new(false);
+        h.visit_nodes(&mut v);
+        v.keys.sort();
+
+        assert!(v.hamts.len() > 1);
+        assert!(v.buckets.is_empty());
+        assert_eq!(v.keys, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn visit_nodes_with_buckets() {
+        let mut h = HashMap::<_, _>::new();
+
+        for i in 0..8 {
+            h = h.insert(CollidingKey(i), i);
+        }
+
+        let mut v = Visitor::new(false);
+        h.visit_nodes(&mut v);
+        v.keys.sort();
+
+        assert_eq!(v.buckets.len(), 1);
+        assert_eq!(v.buffers.len(), 1);
+        assert_eq!(v.keys, (0..8).map(CollidingKey).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn skip_children() {
+        let h = HashMap::<_, _>::new().insert(0, 0).insert(1, 1);
+        let mut v = Visitor::new(true);
+
+        h.visit_nodes(&mut v);
+
+        assert_eq!(v.hamts.len(), 1);
+        assert!(v.keys.is_empty());
+    }
+
+    #[test]
+    fn visit_nodes_in_allocator() {
+        let ps = RefCell::new(BTreeSet::new());
+        let mut h = HashMap::with_hasher_in(
+            DefaultBuildHasher::default(),
+            AllocK(RecordingAllocator(&ps)),
+        );
+
+        for i in 0..8 {
+            h = h.insert(CollidingKey(i), i);
+        }
+
+        let mut v = Visitor::new(false);
+        h.visit_nodes(&mut v);
+
+        assert_eq!(v.buffers.len(), 1);
+        assert!(v
+            .hamts
+            .iter()
+            .chain(&v.buckets)
+            .chain(&v.buffers)
+            .all(|p| ps.borrow().contains(p)));
+    }
+}
@@ -0,0 +1,439 @@
+use crate::hash_map::{DefaultBuildHasher, HashMap, HashMapIterator};
+use crate::pointer::{ArcK, PointerKind};
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{BuildHasher, Hash};
+use core::iter::FromIterator;
+
+/// A persistent hash map from keys to sets of values.
+pub struct HashMultiMap<K: Eq + Hash, V: Eq + Hash, S = DefaultBuildHasher, P: PointerKind = ArcK> {
+    len: usize,
+    map: HashMap<K, Values<V, S, P>, S, P>,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + Eq + Hash, P: PointerKind + Default>
+    HashMultiMap<K, V, DefaultBuildHasher, P>
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<
+        K: Clone + Eq + Hash,
+        V: Clone + Eq + Hash,
+        S: BuildHasher + Clone,
+        P: PointerKind + Default,
+    > HashMultiMap<K, V, S, P>
+{
+    pub fn with_hasher(s: S) -> Self {
+        Self::with_hasher_in(s, Default::default())
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + Eq + Hash, S: BuildHasher + Clone, P: PointerKind>
+    HashMultiMap<K, V, S, P>
+{
+    /// Creates a map which allocates its nodes through a pointer kind.
+    pub fn with_hasher_in(s: S, p: P) -> Self {
+        Self {
+            len: 0,
+            map: HashMap::with_hasher_in(s, p),
+        }
+    }
+
+    pub fn insert(&self, k: K, v: V) -> Self {
+        let mut d = 0;
+        let map = self.map.alter(k, |vs| {
+            let ws = match vs {
+                None => Values::One(v),
+                Some(vs) => vs.insert(v, self.map.hasher(), self.map.pointer_kind()),
+            };
+
+            d = ws.len() - vs.map(Values::len).unwrap_or(0);
+
+            Some(ws)
+        });
+
+        Self {
+            len: self.len + d,
+            map,
+        }
+    }
+
+    pub fn remove<Q: ?Sized + Eq + Hash, R: ?Sized + Eq + Hash>(&self, k: &Q, v: &R) -> Option<Self>
+    where
+        K: Borrow<Q>,
+        V: Borrow<R>,
+    {
+        let (k, vs) = self.map.get_key_value(k)?;
+        let vs = vs.remove(v)?;
+
+        Some(Self {
+            len: self.len - 1,
+            map: self.map.alter(k.clone(), |_| vs),
+        })
+    }
+
+    pub fn remove_all<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<Self>
+    where
+        K: Borrow<Q>,
+    {
+        Some(Self {
+            len: self.len - self.count(k),
+            map: self.map.remove(k)?,
+        })
+    }
+}
+
+impl<K: Eq + Hash, V: Eq + Hash, S: BuildHasher, P: PointerKind> HashMultiMap<K, V, S, P> {
+    pub fn get<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> HashMultiMapValues<'_, V, P>
+    where
+        K: Borrow<Q>,
+    {
+        match self.map.get(k) {
+            Some(vs) => vs.iter(),
+            None => HashMultiMapValues {
+                value: None,
+                iterator: None,
+            },
+        }
+    }
+
+    pub fn contains<Q: ?Sized + Eq + Hash, R: ?Sized + Eq + Hash>(&self, k: &Q, v: &R) -> bool
+    where
+        K: Borrow<Q>,
+        V: Borrow<R>,
+    {
+        match self.map.get(k) {
+            Some(Values::One(w)) => w.borrow() == v,
+            Some(Values::Many(vs)) => vs.get(v).is_some(),
+            None => false,
+        }
+    }
+
+    /// Returns the number of values of a key.
+    pub fn count<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        self.map.get(k).map(Values::len).unwrap_or(0)
+    }
+
+    /// Returns the total number of key-value pairs.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<K: Eq + Hash, V: Eq + Hash, S: BuildHasher + Clone, P: PointerKind> Clone
+    for HashMultiMap<K, V, S, P>
+{
+    fn clone(&self) -> Self {
+        Self {
+            len: self.len,
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K: Debug + Eq + Hash, V: Debug + Eq + Hash, S: BuildHasher, P: PointerKind> Debug
+    for HashMultiMap<K, V, S, P>
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<K: Eq + Hash, V: Eq + Hash, S: BuildHasher, P: PointerKind> PartialEq
+    for HashMultiMap<K, V, S, P>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.map == other.map
+    }
+}
+
+impl<K: Eq + Hash, V: Eq + Hash, S: BuildHasher, P: PointerKind> Eq for HashMultiMap<K, V, S, P> {}
+
+impl<
+        K: Clone + Eq + Hash,
+        V: Clone + Eq + Hash,
+        S: BuildHasher + Clone + Default,
+        P: PointerKind + Default,
+    > Default for HashMultiMap<K, V, S, P>
+{
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<
+        K: Clone + Eq + Hash,
+        V: Clone + Eq + Hash,
+        S: BuildHasher + Clone + Default,
+        P: PointerKind + Default,
+    > FromIterator<(K, V)> for HashMultiMap<K, V, S, P>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(i: I) -> Self {
+        let mut m = Self::default();
+
+        for (k, v) in i {
+            m = m.insert(k, v);
+        }
+
+        m
+    }
+}
+
+// Values of a key. A single value is stored inline and only keys with two or
+// more values have their own sets.
+enum Values<V: Eq + Hash, S, P: PointerKind> {
+    One(V),
+    Many(HashMap<V, (), S, P>),
+}
+
+impl<V: Eq + Hash, S: BuildHasher, P: PointerKind> Values<V, S, P> {
+    fn len(&self) -> usize {
+        match self {
+            Values::One(_) => 1,
+            Values::Many(vs) => vs.len(),
+        }
+    }
+
+    fn iter(&self) -> HashMultiMapValues<'_, V, P> {
+        match self {
+            Values::One(v) => HashMultiMapValues {
+                value: Some(v),
+                iterator: None,
+            },
+            Values::Many(vs) => HashMultiMapValues {
+                value: None,
+                iterator: Some(vs.into_iter()),
+            },
+        }
+    }
+}
+
+impl<V: Clone + Eq + Hash, S: BuildHasher + Clone, P: PointerKind> Values<V, S, P> {
+    fn insert(&self, v: V, s: &S, p: &P) -> Self {
+        Values::Many(match self {
+            Values::One(w) if *w == v => return Values::One(v),
+            Values::One(w) => HashMap::with_hasher_in(s.clone(), p.clone())
+                .insert(w.clone(), ())
+                .insert(v, ()),
+            Values::Many(vs) => vs.insert(v, ()),
+        })
+    }
+
+    // Returns `None` if a value is not found or `Some(None)` if no value is left.
+    fn remove<R: ?Sized + Eq + Hash>(&self, v: &R) -> Option<Option<Self>>
+    where
+        V: Borrow<R>,
+    {
+        match self {
+            Values::One(w) => (w.borrow() == v).then(|| None),
+            Values::Many(vs) => {
+                let vs = vs.remove(v)?;
+
+                Some(Some(if vs.len() == 1 {
+                    Values::One(vs.into_iter().next()?.0.clone())
+                } else {
+                    Values::Many(vs)
+                }))
+            }
+        }
+    }
+}
+
+impl<V: Clone + Eq + Hash, S: Clone, P: PointerKind> Clone for Values<V, S, P> {
+    fn clone(&self) -> Self {
+        match self {
+            Values::One(v) => Values::One(v.clone()),
+            Values::Many(vs) => Values::Many(vs.clone()),
+        }
+    }
+}
+
+impl<V: Eq + Hash, S: BuildHasher, P: PointerKind> PartialEq for Values<V, S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Values::One(v), Values::One(w)) => v == w,
+            (Values::Many(vs), Values::Many(ws)) => vs == ws,
+            _ => false,
+        }
+    }
+}
+
+pub struct HashMultiMapValues<'a, V: Eq + Hash, P: PointerKind = ArcK> {
+    value: Option<&'a V>,
+    iterator: Option<HashMapIterator<'a, V, (), P>>,
+}
+
+impl<'a, V: Eq + Hash, P: PointerKind> Iterator for HashMultiMapValues<'a, V, P> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(v) = self.value.take() {
+            return Some(v);
+        }
+
+        self.iterator.as_mut()?.next().map(|(v, _)| v)
+    }
+}
+
+pub struct HashMultiMapIterator<'a, K: Eq + Hash, V: Eq + Hash, S, P: PointerKind = ArcK> {
+    keys: HashMapIterator<'a, K, Values<V, S, P>, P>,
+    values: Option<(&'a K, HashMultiMapValues<'a, V, P>)>,
+}
+
+impl<'a, K: Eq + Hash, V: Eq + Hash, S: BuildHasher, P: PointerKind> Iterator
+    for HashMultiMapIterator<'a, K, V, S, P>
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, vs)) = &mut self.values {
+                if let Some(v) = vs.next() {
+                    return Some((k, v));
+                }
+            }
+
+            let (k, vs) = self.keys.next()?;
+            self.values = Some((k, vs.iter()));
+        }
+    }
+}
+
+impl<'a, K: Eq + Hash, V: Eq + Hash, S: BuildHasher, P: PointerKind> IntoIterator
+    for &'a HashMultiMap<K, V, S, P>
+{
+    type IntoIter = HashMultiMapIterator<'a, K, V, S, P>;
+    type Item = (&'a K, &'a V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        HashMultiMapIterator {
+            keys: self.map.into_iter(),
+            values: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HashMultiMap;
+    use rand::random;
+    use std::collections::{BTreeSet, HashMap};
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[test]
+    fn new() {
+        HashMultiMap::<usize, usize>::new();
+    }
+
+    #[test]
+    fn insert() {
+        let m = HashMultiMap::<_, _>::new();
+
+        assert_eq!(m.len(), 0);
+        assert_eq!(m.insert(0, 0).len(), 1);
+        assert_eq!(m.insert(0, 0).insert(0, 0).len(), 1);
+        assert_eq!(m.insert(0, 0).insert(0, 1).len(), 2);
+        assert_eq!(m.insert(0, 0).insert(1, 0).len(), 2);
+    }
+
+    #[test]
+    fn remove() {
+        let m = HashMultiMap::<_, _>::new();
+
+        assert_eq!(m.insert(0, 0).remove(&0, &0), Some(m.clone()));
+        assert_eq!(m.insert(0, 0).remove(&0, &1), None);
+        assert_eq!(m.insert(0, 0).remove(&1, &0), None);
+        assert_eq!(
+            m.insert(0, 0).insert(0, 1).remove(&0, &0),
+            Some(m.insert(0, 1))
+        );
+    }
+
+    #[test]
+    fn remove_all() {
+        let m = HashMultiMap::<_, _>::new();
+
+        assert_eq!(m.remove_all(&0), None);
+        assert_eq!(
+            m.insert(0, 0).insert(0, 1).insert(1, 0).remove_all(&0),
+            Some(m.insert(1, 0))
+        );
+        assert_eq!(m.insert(0, 0).insert(0, 1).remove_all(&0).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn get() {
+        let m = HashMultiMap::<_, _>::new()
+            .insert(0, 0)
+            .insert(0, 1)
+            .insert(1, 2);
+
+        assert_eq!(m.get(&0).copied().collect::<BTreeSet<_>>(), [0, 1].into());
+        assert_eq!(m.get(&1).copied().collect::<Vec<_>>(), vec![2]);
+        assert_eq!(m.get(&2).count(), 0);
+        assert_eq!(m.count(&0), 2);
+        assert_eq!(m.count(&2), 0);
+        assert!(m.contains(&0, &1));
+        assert!(!m.contains(&1, &1));
+    }
+
+    #[test]
+    fn insert_remove_many() {
+        let mut m: HashMultiMap<u8, u8> = HashMultiMap::new();
+        let mut n: HashMap<u8, BTreeSet<u8>> = HashMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let k = random::<u8>() % 16;
+            let v = random();
+
+            match random::<u8>() % 4 {
+                0 => {
+                    m = m.remove_all(&k).unwrap_or(m);
+                    n.remove(&k);
+                }
+                1 => {
+                    m = m.remove(&k, &v).unwrap_or(m);
+                    n.entry(k).or_default().remove(&v);
+                }
+                _ => {
+                    m = m.insert(k, v);
+                    n.entry(k).or_default().insert(v);
+                }
+            }
+
+            assert_eq!(m.len(), n.values().map(|vs| vs.len()).sum::<usize>());
+            assert_eq!(m.count(&k), n.get(&k).map(|vs| vs.len()).unwrap_or(0));
+        }
+
+        assert_eq!(
+            m.into_iter()
+                .map(|(k, v)| (*k, *v))
+                .collect::<BTreeSet<_>>(),
+            n.iter()
+                .flat_map(|(k, vs)| vs.iter().map(move |v| (*k, *v)))
+                .collect()
+        );
+    }
+
+    #[test]
+    fn from_iterator() {
+        assert_eq!(
+            vec![(0, 0), (0, 1), (0, 0)]
+                .into_iter()
+                .collect::<HashMultiMap<_, _>>(),
+            HashMultiMap::new().insert(0, 1).insert(0, 0)
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::hash_map::{DefaultBuildHasher, HashMap};
+use crate::ord_map::{OrdMap, OrdMapIterator};
+use crate::pointer::{ArcK, PointerKind};
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{BuildHasher, Hash};
+use core::iter::FromIterator;
+
+/// A persistent hash map which iterates over entries in insertion order.
+pub struct IndexMap<K: Eq + Hash, V: PartialEq, S = DefaultBuildHasher, P: PointerKind = ArcK> {
+    next: u64,
+    // Values are annotated with sequence numbers of their keys.
+    map: HashMap<K, (u64, V), S, P>,
+    keys: OrdMap<u64, K, P>,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, P: PointerKind + Default>
+    IndexMap<K, V, DefaultBuildHasher, P>
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<
+        K: Clone + Eq + Hash,
+        V: Clone + PartialEq,
+        S: BuildHasher + Clone,
+        P: PointerKind + Default,
+    > IndexMap<K, V, S, P>
+{
+    pub fn with_hasher(s: S) -> Self {
+        Self::with_hasher_in(s, Default::default())
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq, S: BuildHasher + Clone, P: PointerKind>
+    IndexMap<K, V, S, P>
+{
+    /// Creates a map which allocates its nodes through a pointer kind.
+    pub fn with_hasher_in(s: S, p: P) -> Self {
+        Self {
+            next: 0,
+            map: HashMap::with_hasher_in(s, p.clone()),
+            keys: OrdMap::new_in(p),
+        }
+    }
+
+    /// Inserts a key-value pair keeping a position of an existing key.
+    pub fn insert(&self, k: K, v: V) -> Self {
+        match self.map.get(&k) {
+            Some(&(i, _)) => Self {
+                next: self.next,
+                map: self.map.insert(k, (i, v)),
+                keys: self.keys.clone(),
+            },
+            None => Self {
+                next: self.next + 1,
+                map: self.map.insert(k.clone(), (self.next, v)),
+                keys: self.keys.insert(self.next, k),
+            },
+        }
+    }
+
+    /// Removes a key preserving an order of the other keys.
+    pub fn remove<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<Self>
+    where
+        K: Borrow<Q>,
+    {
+        let (i, _) = self.map.get(k)?;
+
+        Some(Self {
+            next: self.next,
+            map: self.map.remove(k)?,
+            keys: self.keys.remove(i)?,
+        })
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind> IndexMap<K, V, S, P> {
+    pub fn get<Q: ?Sized + Eq + Hash>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.map.get(k).map(|(_, v)| v)
+    }
+
+    /// Returns a key-value pair at an index in insertion order.
+    pub fn get_index(&self, i: usize) -> Option<(&K, &V)> {
+        let (_, k) = self.keys.get_index(i)?;
+        self.map.get_key_value(k).map(|(k, (_, v))| (k, v))
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.get_index(0)
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.get_index(self.len().checked_sub(1)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, S: Clone, P: PointerKind> Clone for IndexMap<K, V, S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            next: self.next,
+            map: self.map.clone(),
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+impl<K: Debug + Eq + Hash, V: Debug + PartialEq, S: BuildHasher, P: PointerKind> Debug
+    for IndexMap<K, V, S, P>
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self).finish()
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind> PartialEq
+    for IndexMap<K, V, S, P>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.into_iter().eq(other)
+    }
+}
+
+impl<K: Eq + Hash, V: Eq, S: BuildHasher, P: PointerKind> Eq for IndexMap<K, V, S, P> {}
+
+impl<
+        K: Clone + Eq + Hash,
+        V: Clone + PartialEq,
+        S: BuildHasher + Clone + Default,
+        P: PointerKind + Default,
+    > Default for IndexMap<K, V, S, P>
+{
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+impl<
+        K: Clone + Eq + Hash,
+        V: Clone + PartialEq,
+        S: BuildHasher + Clone + Default,
+        P: PointerKind + Default,
+    > FromIterator<(K, V)> for IndexMap<K, V, S, P>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(i: I) -> Self {
+        let mut m = Self::default();
+
+        for (k, v) in i {
+            m = m.insert(k, v);
+        }
+
+        m
+    }
+}
+
+pub struct IndexMapIterator<'a, K: Eq + Hash, V: PartialEq, S, P: PointerKind = ArcK> {
+    map: &'a HashMap<K, (u64, V), S, P>,
+    keys: OrdMapIterator<'a, u64, K, P>,
+}
+
+impl<'a, K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind> Iterator
+    for IndexMapIterator<'a, K, V, S, P>
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, k) = self.keys.next()?;
+        self.map.get_key_value(k).map(|(k, (_, v))| (k, v))
+    }
+}
+
+impl<'a, K: Eq + Hash, V: PartialEq, S: BuildHasher, P: PointerKind> IntoIterator
+    for &'a IndexMap<K, V, S, P>
+{
+    type IntoIter = IndexMapIterator<'a, K, V, S, P>;
+    type Item = (&'a K, &'a V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        IndexMapIterator {
+            map: &self.map,
+            keys: self.keys.into_iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IndexMap;
+    use rand::random;
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[test]
+    fn new() {
+        IndexMap::<usize, usize>::new();
+    }
+
+    #[test]
+    fn insert() {
+        let m = IndexMap::<_, _>::new()
+            .insert(2, 0)
+            .insert(0, 1)
+            .insert(1, 2);
+
+        assert_eq!(m.len(), 3);
+        assert_eq!(
+            m.into_iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec![2, 0, 1]
+        );
+        assert_eq!(m.insert(2, 42).get_index(0), Some((&2, &42)));
+        assert_eq!(m.insert(2, 42).len(), 3);
+    }
+
+    #[test]
+    fn remove() {
+        let m = IndexMap::<_, _>::new()
+            .insert(2, 0)
+            .insert(0, 1)
+            .insert(1, 2);
+
+        assert_eq!(m.remove(&3), None);
+        assert_eq!(
+            m.remove(&0),
+            Some(IndexMap::new().insert(2, 0).insert(1, 2))
+        );
+        assert_eq!(m.remove(&2).unwrap().first(), Some((&0, &1)));
+        assert_eq!(m.remove(&1).unwrap().last(), Some((&0, &1)));
+    }
+
+    #[test]
+    fn get_index() {
+        let m = IndexMap::<_, _>::new().insert(2, 0).insert(0, 1);
+
+        assert_eq!(m.get_index(0), Some((&2, &0)));
+        assert_eq!(m.get_index(1), Some((&0, &1)));
+        assert_eq!(m.get_index(2), None);
+        assert_eq!(IndexMap::<usize, usize>::new().last(), None);
+    }
+
+    #[test]
+    fn equality() {
+        let m = IndexMap::<_, _>::new().insert(0, 0).insert(1, 0);
+
+        assert_eq!(m, m.insert(0, 0));
+        assert_eq!(m, m.remove(&1).unwrap().insert(1, 0));
+        assert_ne!(m, IndexMap::new().insert(1, 0).insert(0, 0));
+    }
+
+    #[test]
+    fn insert_remove_many() {
+        let mut m: IndexMap<u16, u16> = IndexMap::new();
+        let mut v: Vec<(u16, u16)> = vec![];
+
+        for _ in 0..NUM_ITERATIONS {
+            let k = random::<u16>() % 256;
+            let i = v.iter().position(|(kk, _)| *kk == k);
+
+            if random() {
+                let x = random();
+                m = m.insert(k, x);
+
+                match i {
+                    Some(i) => v[i].1 = x,
+                    None => v.push((k, x)),
+                }
+            } else {
+                m = m.remove(&k).unwrap_or(m);
+
+                if let Some(i) = i {
+                    v.remove(i);
+                }
+            }
+
+            assert_eq!(m.len(), v.len());
+            assert_eq!(m.get(&k), v.iter().find(|(kk, _)| *kk == k).map(|(_, x)| x));
+        }
+
+        for (i, (k, x)) in v.iter().enumerate() {
+            assert_eq!(m.get_index(i), Some((k, x)));
+            assert_eq!(m.get(k), Some(x));
+        }
+
+        assert_eq!(m.into_iter().map(|(k, x)| (*k, *x)).collect::<Vec<_>>(), v);
+    }
+
+    #[test]
+    fn from_iterator() {
+        assert_eq!(
+            vec![(1, 0), (0, 0), (1, 1)]
+                .into_iter()
+                .collect::<IndexMap<_, _>>(),
+            IndexMap::new().insert(1, 1).insert(0, 0)
+        );
+    }
+}
@@ -1,10 +1,49 @@
-#![feature(test)]
+#![no_std]
+#![cfg_attr(all(test, feature = "nightly"), feature(test))]
+#![allow(clippy::upper_case_acronyms)]
 
+#[macro_use]
+extern crate alloc;
 #[cfg(test)]
 extern crate rand;
-#[cfg(test)]
+#[cfg(any(feature = "std", test))]
+extern crate std;
+#[cfg(all(test, feature = "nightly"))]
 extern crate test;
 
+mod bi_map;
+mod graph;
+mod hash_bag;
 mod hash_map;
+mod hash_multi_map;
+mod index_map;
+mod list;
+mod ord_map;
+mod pointer;
+mod priority_queue;
+mod queue;
+mod trie_map;
+mod union_find;
+pub mod unsync;
+mod vector;
 
-pub use hash_map::HashMap;
+pub use bi_map::BiMap;
+pub use graph::Graph;
+pub use hash_bag::HashBag;
+#[cfg(feature = "archive")]
+pub use hash_map::archive;
+#[cfg(feature = "std")]
+pub use hash_map::snapshot;
+pub use hash_map::{
+    DefaultBuildHasher, DefaultHasher, HashMap, InvariantError, NodeVisitor, Stats,
+};
+pub use hash_multi_map::HashMultiMap;
+pub use index_map::IndexMap;
+pub use list::List;
+pub use ord_map::OrdMap;
+pub use pointer::{AllocK, ArcK, Global, LeakK, Leaked, NodeAllocator, PointerKind, RcK};
+pub use priority_queue::PriorityQueue;
+pub use queue::Queue;
+pub use trie_map::TrieMap;
+pub use union_find::UnionFind;
+pub use vector::Vector;
@@ -0,0 +1,349 @@
+use crate::pointer::{ArcK, PointerKind};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use core::iter::FromIterator;
+
+/// A persistent singly-linked list.
+pub struct List<T, P: PointerKind = ArcK> {
+    len: usize,
+    node: Option<P::Pointer<Node<T, P>>>,
+    pointer_kind: P,
+}
+
+struct Node<T, P: PointerKind> {
+    head: T,
+    tail: Option<P::Pointer<Node<T, P>>>,
+}
+
+impl<T, P: PointerKind + Default> List<T, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, P: PointerKind> List<T, P> {
+    /// Creates a list which allocates its nodes through a pointer kind.
+    pub fn new_in(p: P) -> Self {
+        Self {
+            len: 0,
+            node: None,
+            pointer_kind: p,
+        }
+    }
+
+    pub fn cons(&self, x: T) -> Self {
+        Self {
+            len: self.len + 1,
+            node: Some(self.pointer_kind.pointer(Node {
+                head: x,
+                tail: self.node.clone(),
+            })),
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+
+    pub fn head(&self) -> Option<&T> {
+        self.node.as_ref().map(|n| &n.head)
+    }
+
+    pub fn tail(&self) -> Option<Self> {
+        self.node.as_ref().map(|n| Self {
+            len: self.len - 1,
+            node: n.tail.clone(),
+            pointer_kind: self.pointer_kind.clone(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn pointer_kind(&self) -> &P {
+        &self.pointer_kind
+    }
+
+    /// Iterates over elements from the last one.
+    pub(crate) fn iter_rev(&self) -> ListReverseIterator<'_, T, P> {
+        ListReverseIterator {
+            segments: self
+                .node
+                .as_deref()
+                .map(|n| (n, self.len))
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl<T: Clone, P: PointerKind> List<T, P> {
+    pub fn reverse(&self) -> Self {
+        let mut l = Self::new_in(self.pointer_kind.clone());
+
+        for x in self {
+            l = l.cons(x.clone());
+        }
+
+        l
+    }
+}
+
+impl<T, P: PointerKind> Drop for List<T, P> {
+    // Nodes are dropped one by one so that long lists do not overflow stacks.
+    fn drop(&mut self) {
+        let mut n = self.node.take();
+
+        while let Some(m) = n {
+            n = P::into_inner(m).and_then(|mut m| m.tail.take());
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for List<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            len: self.len,
+            node: self.node.clone(),
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<T: Debug, P: PointerKind> Debug for List<T, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq, P: PointerKind> PartialEq for List<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && match (&self.node, &other.node) {
+                (Some(n), Some(m)) => P::ptr_eq(n, m) || self.into_iter().eq(other),
+                _ => true,
+            }
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for List<T, P> {}
+
+impl<T, P: PointerKind + Default> Default for List<T, P> {
+    fn default() -> Self {
+        Self::new_in(Default::default())
+    }
+}
+
+impl<T, P: PointerKind + Default> FromIterator<T> for List<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(i: I) -> Self {
+        let xs: Vec<T> = i.into_iter().collect();
+        let mut l = Self::default();
+
+        for x in xs.into_iter().rev() {
+            l = l.cons(x);
+        }
+
+        l
+    }
+}
+
+pub struct ListIterator<'a, T, P: PointerKind = ArcK> {
+    node: Option<&'a Node<T, P>>,
+}
+
+impl<'a, T, P: PointerKind> Iterator for ListIterator<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.node?;
+        self.node = n.tail.as_deref();
+        Some(&n.head)
+    }
+}
+
+impl<'a, T, P: PointerKind> IntoIterator for &'a List<T, P> {
+    type IntoIter = ListIterator<'a, T, P>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ListIterator {
+            node: self.node.as_deref(),
+        }
+    }
+}
+
+/// An iterator over elements of a list in reverse order.
+///
+/// Lists are split into halves recursively instead of being copied. It takes
+/// logarithmic time per element and keeps a logarithmic number of positions.
+pub(crate) struct ListReverseIterator<'a, T, P: PointerKind> {
+    // Segments of nodes and their lengths iterated over from the last one.
+    segments: Vec<(&'a Node<T, P>, usize)>,
+}
+
+impl<'a, T, P: PointerKind> Iterator for ListReverseIterator<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (mut n, mut len) = self.segments.pop()?;
+
+        while len > 1 {
+            let h = len / 2;
+            self.segments.push((n, h));
+
+            for _ in 0..h {
+                n = n.tail.as_deref()?;
+            }
+
+            len -= h;
+        }
+
+        Some(&n.head)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::List;
+    use crate::pointer::{LeakK, RcK};
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[test]
+    fn new() {
+        List::<usize>::new();
+    }
+
+    #[test]
+    fn cons() {
+        let l = List::<_>::new();
+
+        assert_eq!(l.len(), 0);
+        assert_eq!(l.head(), None);
+        assert_eq!(l.cons(0).len(), 1);
+        assert_eq!(l.cons(0).head(), Some(&0));
+        assert_eq!(l.cons(0).cons(1).head(), Some(&1));
+    }
+
+    #[test]
+    fn tail() {
+        let l = List::<_>::new();
+
+        assert_eq!(l.tail(), None);
+        assert_eq!(l.cons(0).tail(), Some(l.clone()));
+        assert_eq!(l.cons(0).cons(1).tail(), Some(l.cons(0)));
+    }
+
+    #[test]
+    fn share_tails() {
+        let l: List<usize> = (0..NUM_ITERATIONS).collect();
+        let ll = l.cons(42);
+
+        drop(l);
+
+        assert_eq!(ll.len(), NUM_ITERATIONS + 1);
+        assert!(ll
+            .tail()
+            .unwrap()
+            .into_iter()
+            .copied()
+            .eq(0..NUM_ITERATIONS));
+    }
+
+    #[test]
+    fn reverse() {
+        let l: List<usize> = (0..NUM_ITERATIONS).collect();
+
+        assert!(l
+            .reverse()
+            .into_iter()
+            .copied()
+            .eq((0..NUM_ITERATIONS).rev()));
+        assert_eq!(l.reverse().reverse(), l);
+    }
+
+    #[test]
+    fn iter_rev() {
+        for n in 0..64 {
+            let l: List<usize> = (0..n).collect();
+
+            assert!(l.iter_rev().copied().eq((0..n).rev()));
+        }
+    }
+
+    #[test]
+    fn from_iterator() {
+        assert_eq!(
+            Vec::<usize>::new().into_iter().collect::<List<_>>(),
+            List::new()
+        );
+        assert_eq!(
+            vec![0, 1].into_iter().collect::<List<_>>(),
+            List::new().cons(1).cons(0)
+        );
+    }
+
+    #[test]
+    fn equality() {
+        let l: List<usize> = (0..NUM_ITERATIONS).collect();
+
+        assert_eq!(l, (0..NUM_ITERATIONS).collect());
+        assert_ne!(l, l.tail().unwrap());
+        assert_ne!(l.cons(0), l.cons(1));
+    }
+
+    #[test]
+    fn drop_long_list() {
+        let mut l = List::<_>::new();
+
+        for i in 0..1 << 20 {
+            l = l.cons(i);
+        }
+    }
+
+    #[test]
+    fn drop_long_rc_list() {
+        let mut l = List::<usize, RcK>::default();
+
+        for i in 0..1 << 20 {
+            l = l.cons(i);
+        }
+    }
+
+    #[test]
+    fn drop_leaked_list() {
+        let l: List<usize, LeakK> = (0..NUM_ITERATIONS).collect();
+        let ll = l.tail().unwrap();
+
+        drop(l);
+
+        assert_eq!(ll.len(), NUM_ITERATIONS - 1);
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod bench {
+    use super::List;
+    use test::Bencher;
+
+    #[bench]
+    fn bench_cons_1000(b: &mut Bencher) {
+        b.iter(|| {
+            let mut l = List::<_>::new();
+
+            for i in 0..1000 {
+                l = l.cons(i);
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_iterate_1000(b: &mut Bencher) {
+        let l: List<usize> = (0..1000).collect();
+
+        b.iter(|| l.into_iter().count());
+    }
+}
@@ -0,0 +1,480 @@
+mod node;
+
+use crate::pointer::{ArcK, PointerKind};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Formatter};
+use core::iter::FromIterator;
+use core::ops::{Bound, RangeBounds};
+use node::{offset, Node};
+
+/// A persistent ordered map as a B-tree.
+pub struct OrdMap<K, V, P: PointerKind = ArcK> {
+    root: P::Pointer<Node<K, V, P>>,
+    height: usize,
+    pointer_kind: P,
+}
+
+impl<K: Clone + Ord, V: Clone, P: PointerKind + Default> OrdMap<K, V, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, P: PointerKind> OrdMap<K, V, P> {
+    /// Creates a map which allocates its nodes through a pointer kind.
+    pub fn new_in(p: P) -> Self {
+        Self {
+            root: p.pointer(Node::leaf(vec![], &p)),
+            height: 0,
+            pointer_kind: p,
+        }
+    }
+}
+
+impl<K: Clone + Ord, V: Clone, P: PointerKind> OrdMap<K, V, P> {
+    pub fn insert(&self, k: K, v: V) -> Self {
+        let (ns, _) = self.root.insert(k, v, &self.pointer_kind);
+        self.branch(ns, self.height + 1)
+    }
+
+    pub fn remove<Q: ?Sized + Ord>(&self, k: &Q) -> Option<Self>
+    where
+        K: Borrow<Q>,
+    {
+        let n = self.root.remove(k, &self.pointer_kind)?;
+
+        Some(match n {
+            Node::Branch(_, _, cs) if cs.len() == 1 => self.node(cs[0].clone(), self.height - 1),
+            n => self.node(self.pointer_kind.pointer(n), self.height),
+        })
+    }
+
+    /// Splits a map into one with keys less than a key and one with the rest.
+    pub fn split<Q: ?Sized + Ord>(&self, k: &Q) -> (Self, Self)
+    where
+        K: Borrow<Q>,
+    {
+        self.split_node(&self.root, self.height, self.root.rank(k, false))
+    }
+
+    fn split_node(&self, n: &P::Pointer<Node<K, V, P>>, height: usize, i: usize) -> (Self, Self) {
+        let p = &self.pointer_kind;
+
+        if i == 0 {
+            return (self.empty(), self.node(n.clone(), height));
+        } else if i == n.len() {
+            return (self.node(n.clone(), height), self.empty());
+        }
+
+        match &**n {
+            Node::Leaf(kvs) => (
+                self.node(p.pointer(Node::leaf(kvs[..i].to_vec(), p)), 0),
+                self.node(p.pointer(Node::leaf(kvs[i..].to_vec(), p)), 0),
+            ),
+            Node::Branch(_, ss, cs) => {
+                let j = ss.partition_point(|&s| s <= i);
+                let (l, r) = self.split_node(&cs[j], height - 1, i - offset(ss, j));
+
+                (
+                    self.branch(cs[..j].to_vec(), height).concat(&l),
+                    r.concat(&self.branch(cs[j + 1..].to_vec(), height)),
+                )
+            }
+        }
+    }
+
+    // Keys in the first map must be less than ones in the second.
+    fn concat(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        } else if other.is_empty() {
+            return self.clone();
+        }
+
+        let h = self.height.max(other.height);
+
+        self.branch(
+            Node::<K, V, P>::join(
+                &self.root,
+                self.height,
+                &other.root,
+                other.height,
+                &self.pointer_kind,
+            ),
+            h + 1,
+        )
+    }
+
+    fn branch(&self, cs: Vec<P::Pointer<Node<K, V, P>>>, height: usize) -> Self {
+        match cs.len() {
+            0 => self.empty(),
+            1 => self.node(cs[0].clone(), height - 1),
+            _ => self.node(
+                self.pointer_kind
+                    .pointer(Node::branch(cs, &self.pointer_kind)),
+                height,
+            ),
+        }
+    }
+
+    fn empty(&self) -> Self {
+        Self::new_in(self.pointer_kind.clone())
+    }
+}
+
+impl<K: Ord, V, P: PointerKind> OrdMap<K, V, P> {
+    pub fn get<Q: ?Sized + Ord>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.root.get(k)
+    }
+
+    /// Returns a key-value pair at an index in key order.
+    pub fn get_index(&self, i: usize) -> Option<(&K, &V)> {
+        self.root.get_index(i)
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.get_index(0)
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.get_index(self.len().checked_sub(1)?)
+    }
+
+    pub fn range<Q: ?Sized + Ord, R: RangeBounds<Q>>(&self, r: R) -> OrdMapIterator<'_, K, V, P>
+    where
+        K: Borrow<Q>,
+    {
+        let start = match r.start_bound() {
+            Bound::Included(k) => self.root.rank(k, false),
+            Bound::Excluded(k) => self.root.rank(k, true),
+            Bound::Unbounded => 0,
+        };
+        let end = match r.end_bound() {
+            Bound::Included(k) => self.root.rank(k, true),
+            Bound::Excluded(k) => self.root.rank(k, false),
+            Bound::Unbounded => self.len(),
+        };
+
+        OrdMapIterator::new(&self.root, start, end.saturating_sub(start))
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn pointer_kind(&self) -> &P {
+        &self.pointer_kind
+    }
+
+    fn node(&self, n: P::Pointer<Node<K, V, P>>, height: usize) -> Self {
+        Self {
+            root: n,
+            height,
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<K, V, P: PointerKind> Clone for OrdMap<K, V, P> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            height: self.height,
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<K: Debug + Ord, V: Debug, P: PointerKind> Debug for OrdMap<K, V, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self).finish()
+    }
+}
+
+impl<K: Ord, V: PartialEq, P: PointerKind> PartialEq for OrdMap<K, V, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && (P::ptr_eq(&self.root, &other.root) || self.into_iter().eq(other))
+    }
+}
+
+impl<K: Ord, V: Eq, P: PointerKind> Eq for OrdMap<K, V, P> {}
+
+impl<K, V, P: PointerKind + Default> Default for OrdMap<K, V, P> {
+    fn default() -> Self {
+        Self::new_in(Default::default())
+    }
+}
+
+impl<K: Clone + Ord, V: Clone, P: PointerKind + Default> FromIterator<(K, V)> for OrdMap<K, V, P> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(i: I) -> Self {
+        let mut m = Self::default();
+
+        for (k, v) in i {
+            m = m.insert(k, v);
+        }
+
+        m
+    }
+}
+
+pub struct OrdMapIterator<'a, K, V, P: PointerKind = ArcK> {
+    nodes: Vec<(&'a Node<K, V, P>, usize)>,
+    len: usize,
+}
+
+impl<'a, K, V, P: PointerKind> OrdMapIterator<'a, K, V, P> {
+    fn new(mut n: &'a Node<K, V, P>, mut i: usize, len: usize) -> Self {
+        let mut nodes = vec![];
+
+        while let Node::Branch(_, ss, cs) = n {
+            let j = ss.partition_point(|&s| s <= i).min(cs.len() - 1);
+
+            nodes.push((n, j + 1));
+            i -= offset(ss, j);
+            n = &cs[j];
+        }
+
+        nodes.push((n, i));
+
+        Self { nodes, len }
+    }
+}
+
+impl<'a, K, V, P: PointerKind> Iterator for OrdMapIterator<'a, K, V, P> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.len > 0 {
+            let (n, i) = self.nodes.last_mut()?;
+            let n: &'a Node<K, V, P> = n;
+
+            match n {
+                Node::Leaf(kvs) => {
+                    if let Some((k, v)) = kvs.get(*i) {
+                        *i += 1;
+                        self.len -= 1;
+                        return Some((k, v));
+                    }
+                }
+                Node::Branch(_, _, cs) => {
+                    if let Some(c) = cs.get(*i) {
+                        *i += 1;
+                        self.nodes.push((c, 0));
+                        continue;
+                    }
+                }
+            }
+
+            self.nodes.pop();
+        }
+
+        None
+    }
+}
+
+impl<'a, K: Ord, V, P: PointerKind> IntoIterator for &'a OrdMap<K, V, P> {
+    type IntoIter = OrdMapIterator<'a, K, V, P>;
+    type Item = (&'a K, &'a V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        OrdMapIterator::new(&self.root, 0, self.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OrdMap;
+    use rand::random;
+    use std::collections::BTreeMap;
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    fn is_balanced<K: Clone + Ord, V>(m: &OrdMap<K, V>) -> bool {
+        m.root.is_balanced(m.height, true)
+    }
+
+    fn random_map() -> (OrdMap<u16, u16>, BTreeMap<u16, u16>) {
+        let kvs: Vec<(u16, u16)> = (0..random::<usize>() % NUM_ITERATIONS)
+            .map(|_| (random(), random()))
+            .collect();
+
+        (kvs.iter().cloned().collect(), kvs.iter().cloned().collect())
+    }
+
+    #[test]
+    fn new() {
+        OrdMap::<usize, usize>::new();
+    }
+
+    #[test]
+    fn insert() {
+        let m = OrdMap::<_, _>::new();
+
+        assert_eq!(m.len(), 0);
+        assert_eq!(m.insert(0, 0).len(), 1);
+        assert_eq!(m.insert(0, 0).insert(0, 0).len(), 1);
+        assert_eq!(m.insert(0, 0).insert(1, 0).len(), 2);
+    }
+
+    #[test]
+    fn insert_many_in_order() {
+        let mut m = OrdMap::new();
+
+        for i in 0..NUM_ITERATIONS {
+            m = m.insert(i, i);
+
+            assert_eq!(m.len(), i + 1);
+            assert!(is_balanced(&m));
+        }
+    }
+
+    #[test]
+    fn remove() {
+        let m = OrdMap::<_, _>::new();
+
+        assert_eq!(m.insert(0, 0).remove(&0), Some(m.clone()));
+        assert_eq!(m.insert(0, 0).remove(&1), None);
+        assert_eq!(m.insert(0, 0).insert(1, 0).remove(&0), Some(m.insert(1, 0)));
+    }
+
+    #[test]
+    fn insert_remove_many() {
+        let mut m: OrdMap<i16, i16> = OrdMap::new();
+        let mut n = BTreeMap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let k = random();
+
+            if random() {
+                m = m.insert(k, k);
+                n.insert(k, k);
+            } else {
+                m = m.remove(&k).unwrap_or(m);
+                n.remove(&k);
+            }
+
+            assert_eq!(m.len(), n.len());
+            assert_eq!(m.get(&k), n.get(&k));
+            assert!(is_balanced(&m));
+        }
+
+        assert!(m.into_iter().eq(&n));
+    }
+
+    #[test]
+    fn get_index() {
+        let (m, n) = random_map();
+
+        for (i, kv) in n.iter().enumerate() {
+            assert_eq!(m.get_index(i), Some(kv));
+        }
+
+        assert_eq!(m.get_index(n.len()), None);
+    }
+
+    #[test]
+    fn first_and_last() {
+        let (m, n) = random_map();
+
+        assert_eq!(m.first(), n.iter().next());
+        assert_eq!(m.last(), n.iter().next_back());
+        assert_eq!(OrdMap::<usize, usize>::new().first(), None);
+        assert_eq!(OrdMap::<usize, usize>::new().last(), None);
+    }
+
+    #[test]
+    fn range() {
+        for _ in 0..64 {
+            let (m, n) = random_map();
+            let (x, y) = (random::<u16>(), random::<u16>());
+            let (x, y) = (x.min(y), x.max(y));
+
+            assert!(m.range(x..y).eq(n.range(x..y)));
+            assert!(m.range(x..=y).eq(n.range(x..=y)));
+            assert!(m.range(x..).eq(n.range(x..)));
+            assert!(m.range(..y).eq(n.range(..y)));
+            assert!(m.range::<u16, _>(..).eq(n.range::<u16, _>(..)));
+        }
+    }
+
+    #[test]
+    fn split() {
+        for _ in 0..64 {
+            let (m, n) = random_map();
+            let k = random();
+            let (l, r) = m.split(&k);
+
+            assert!(is_balanced(&l));
+            assert!(is_balanced(&r));
+            assert!(l.into_iter().eq(n.range(..k)));
+            assert!(r.into_iter().eq(n.range(k..)));
+        }
+    }
+
+    #[test]
+    fn split_and_insert() {
+        let (m, n) = random_map();
+        let (l, r) = m.split(&(u16::MAX / 2));
+        let mut l = l;
+
+        for (k, v) in &r {
+            l = l.insert(*k, *v);
+        }
+
+        assert!(is_balanced(&l));
+        assert!(l.into_iter().eq(&n));
+    }
+
+    #[test]
+    fn equality() {
+        let (m, n) = random_map();
+
+        assert_eq!(m, n.iter().rev().map(|(k, v)| (*k, *v)).collect());
+        assert_ne!(m, m.insert(0, 0).insert(1, 1).remove(&0).unwrap());
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod bench {
+    use super::OrdMap;
+    use test::Bencher;
+
+    #[bench]
+    fn bench_insert_1000(b: &mut Bencher) {
+        b.iter(|| {
+            let mut m = OrdMap::<_, _>::new();
+
+            for i in 0..1000 {
+                m = m.insert(i, i);
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_get_1000(b: &mut Bencher) {
+        let m: OrdMap<usize, usize> = (0..1000).map(|i| (i, i)).collect();
+
+        b.iter(|| {
+            for i in 0..1000 {
+                m.get(&i);
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_iterate_1000(b: &mut Bencher) {
+        let m: OrdMap<usize, usize> = (0..1000).map(|i| (i, i)).collect();
+
+        b.iter(|| m.into_iter().count());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::hash_map::NUM_ENTRIES;
+use crate::pointer::PointerKind;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+
+pub const MAX_WIDTH: usize = NUM_ENTRIES;
+pub const MIN_WIDTH: usize = MAX_WIDTH / 2;
+
+pub enum Node<K, V, P: PointerKind> {
+    Leaf(P::Array<(K, V)>),
+    // Children are annotated with their minimum keys and cumulative sizes.
+    Branch(
+        P::Array<K>,
+        P::Array<usize>,
+        P::Array<P::Pointer<Node<K, V, P>>>,
+    ),
+}
+
+impl<K: Ord, V, P: PointerKind> Node<K, V, P> {
+    pub fn len(&self) -> usize {
+        match self {
+            Node::Leaf(kvs) => kvs.len(),
+            Node::Branch(_, ss, _) => ss.last().copied().unwrap_or(0),
+        }
+    }
+
+    pub fn get<Q: ?Sized + Ord>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        match self {
+            Node::Leaf(kvs) => kvs
+                .binary_search_by(|(kk, _)| kk.borrow().cmp(k))
+                .ok()
+                .map(|i| &kvs[i].1),
+            Node::Branch(ks, _, cs) => cs[child_index(ks, k)].get(k),
+        }
+    }
+
+    pub fn get_index(&self, i: usize) -> Option<(&K, &V)> {
+        match self {
+            Node::Leaf(kvs) => kvs.get(i).map(|(k, v)| (k, v)),
+            Node::Branch(_, ss, cs) => {
+                let j = ss.partition_point(|&s| s <= i);
+                cs.get(j)?.get_index(i - offset(ss, j))
+            }
+        }
+    }
+
+    /// Returns the number of keys less than (or equal to) a key.
+    pub fn rank<Q: ?Sized + Ord>(&self, k: &Q, inclusive: bool) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        match self {
+            Node::Leaf(kvs) => kvs.partition_point(|(kk, _)| match kk.borrow().cmp(k) {
+                Ordering::Less => true,
+                Ordering::Equal => inclusive,
+                Ordering::Greater => false,
+            }),
+            Node::Branch(ks, ss, cs) => {
+                let j = child_index(ks, k);
+                offset(ss, j) + cs[j].rank(k, inclusive)
+            }
+        }
+    }
+
+    pub fn children(&self) -> &[P::Pointer<Node<K, V, P>>] {
+        match self {
+            Node::Leaf(_) => unreachable!(),
+            Node::Branch(_, _, cs) => cs,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        match self {
+            Node::Leaf(kvs) => kvs.len(),
+            Node::Branch(_, _, cs) => cs.len(),
+        }
+    }
+
+    fn first_key(&self) -> &K {
+        match self {
+            Node::Leaf(kvs) => &kvs[0].0,
+            Node::Branch(ks, _, _) => &ks[0],
+        }
+    }
+
+    #[cfg(test)]
+    pub fn is_balanced(&self, height: usize, root: bool) -> bool
+    where
+        K: Clone,
+    {
+        let w = self.width();
+
+        w <= MAX_WIDTH
+            && match self {
+                Node::Leaf(kvs) => {
+                    height == 0
+                        && (root || w >= MIN_WIDTH)
+                        && kvs.windows(2).all(|kvs| kvs[0].0 < kvs[1].0)
+                }
+                Node::Branch(ks, ss, cs) => {
+                    let mut s = 0;
+
+                    height > 0
+                        && w >= if root { 2 } else { MIN_WIDTH }
+                        && cs.iter().enumerate().all(|(i, c)| {
+                            s += c.len();
+                            ks[i] == *c.first_key()
+                                && ss[i] == s
+                                && c.is_balanced(height - 1, false)
+                        })
+                        && ks.windows(2).all(|ks| ks[0] < ks[1])
+                }
+            }
+    }
+}
+
+impl<K, V, P: PointerKind> Node<K, V, P> {
+    pub fn leaf(kvs: Vec<(K, V)>, p: &P) -> Self {
+        Node::Leaf(p.array(kvs))
+    }
+}
+
+impl<K: Clone + Ord, V: Clone, P: PointerKind> Node<K, V, P> {
+    pub fn branch(cs: Vec<P::Pointer<Node<K, V, P>>>, p: &P) -> Self {
+        let mut s = 0;
+
+        Node::Branch(
+            p.array(cs.iter().map(|c| c.first_key().clone()).collect()),
+            p.array(
+                cs.iter()
+                    .map(|c| {
+                        s += c.len();
+                        s
+                    })
+                    .collect(),
+            ),
+            p.array(cs),
+        )
+    }
+
+    pub fn insert(&self, k: K, v: V, p: &P) -> (Vec<P::Pointer<Self>>, bool) {
+        match self {
+            Node::Leaf(kvs) => {
+                let mut kvs = kvs.to_vec();
+
+                let new = match kvs.binary_search_by(|(kk, _)| kk.cmp(&k)) {
+                    Ok(i) => {
+                        kvs[i] = (k, v);
+                        false
+                    }
+                    Err(i) => {
+                        kvs.insert(i, (k, v));
+                        true
+                    }
+                };
+
+                (Self::split(kvs, Self::leaf, p), new)
+            }
+            Node::Branch(ks, _, cs) => {
+                let j = child_index(ks, &k);
+                let (ns, new) = cs[j].insert(k, v, p);
+                let mut cs = cs.to_vec();
+                cs.splice(j..j + 1, ns);
+
+                (Self::split(cs, Self::branch, p), new)
+            }
+        }
+    }
+
+    // A returned node can have less than the minimum number of entries.
+    pub fn remove<Q: ?Sized + Ord>(&self, k: &Q, p: &P) -> Option<Self>
+    where
+        K: Borrow<Q>,
+    {
+        match self {
+            Node::Leaf(kvs) => {
+                let i = kvs.binary_search_by(|(kk, _)| kk.borrow().cmp(k)).ok()?;
+                let mut kvs = kvs.to_vec();
+                kvs.remove(i);
+
+                Some(Self::leaf(kvs, p))
+            }
+            Node::Branch(ks, _, cs) => {
+                let j = child_index(ks, k);
+                let c = p.pointer(cs[j].remove(k, p)?);
+                let mut cs = cs.to_vec();
+
+                if c.width() >= MIN_WIDTH {
+                    cs[j] = c;
+                } else if j > 0 {
+                    let ns = Self::merge(&cs[j - 1], &c, p);
+                    cs.splice(j - 1..j + 1, ns);
+                } else {
+                    let ns = Self::merge(&c, &cs[j + 1], p);
+                    cs.splice(j..j + 2, ns);
+                }
+
+                Some(Self::branch(cs, p))
+            }
+        }
+    }
+
+    // Joins two trees into one or two nodes at the height of the higher tree.
+    pub fn join(
+        a: &P::Pointer<Self>,
+        ha: usize,
+        b: &P::Pointer<Self>,
+        hb: usize,
+        p: &P,
+    ) -> Vec<P::Pointer<Self>> {
+        match ha.cmp(&hb) {
+            Ordering::Equal => Self::merge(a, b, p),
+            Ordering::Greater => {
+                let mut cs = a.children().to_vec();
+                let c = cs.pop().unwrap();
+                cs.extend(Self::join(&c, ha - 1, b, hb, p));
+                Self::split(cs, Self::branch, p)
+            }
+            Ordering::Less => {
+                let cs = b.children();
+                let mut ds = Self::join(a, ha, &cs[0], hb - 1, p);
+                ds.extend(cs[1..].iter().cloned());
+                Self::split(ds, Self::branch, p)
+            }
+        }
+    }
+
+    fn merge(a: &P::Pointer<Self>, b: &P::Pointer<Self>, p: &P) -> Vec<P::Pointer<Self>> {
+        if a.width() >= MIN_WIDTH && b.width() >= MIN_WIDTH {
+            return vec![a.clone(), b.clone()];
+        }
+
+        match (&**a, &**b) {
+            (Node::Leaf(xs), Node::Leaf(ys)) => {
+                Self::split(xs.iter().chain(ys.iter()).cloned().collect(), Self::leaf, p)
+            }
+            (Node::Branch(_, _, cs), Node::Branch(_, _, ds)) => Self::split(
+                cs.iter().chain(ds.iter()).cloned().collect(),
+                Self::branch,
+                p,
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    fn split<U>(mut xs: Vec<U>, f: impl Fn(Vec<U>, &P) -> Self, p: &P) -> Vec<P::Pointer<Self>> {
+        if xs.len() <= MAX_WIDTH {
+            vec![p.pointer(f(xs, p))]
+        } else {
+            let ys = xs.split_off(xs.len() / 2);
+            vec![p.pointer(f(xs, p)), p.pointer(f(ys, p))]
+        }
+    }
+}
+
+impl<K, V, P: PointerKind> Clone for Node<K, V, P> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Leaf(kvs) => Node::Leaf(kvs.clone()),
+            Node::Branch(ks, ss, cs) => Node::Branch(ks.clone(), ss.clone(), cs.clone()),
+        }
+    }
+}
+
+fn child_index<K: Borrow<Q>, Q: ?Sized + Ord>(ks: &[K], k: &Q) -> usize {
+    ks.partition_point(|kk| kk.borrow() <= k).saturating_sub(1)
+}
+
+pub fn offset(ss: &[usize], j: usize) -> usize {
+    if j == 0 {
+        0
+    } else {
+        ss[j - 1]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MAX_WIDTH, MIN_WIDTH};
+    use crate::pointer::{ArcK, PointerKind};
+
+    type Node = super::Node<usize, usize, ArcK>;
+
+    fn leaf(ks: impl Iterator<Item = usize>) -> <ArcK as PointerKind>::Pointer<Node> {
+        ArcK.pointer(Node::leaf(ks.map(|k| (k, k)).collect(), &ArcK))
+    }
+
+    #[test]
+    fn branch() {
+        let n = Node::branch(vec![leaf(0..2), leaf(2..5)], &ArcK);
+
+        assert_eq!(n.len(), 5);
+        assert_eq!(n.get(&0), Some(&0));
+        assert_eq!(n.get(&4), Some(&4));
+        assert_eq!(n.get(&5), None);
+        assert_eq!(n.get_index(3), Some((&3, &3)));
+        assert_eq!(n.get_index(5), None);
+    }
+
+    #[test]
+    fn rank() {
+        let n = Node::branch(
+            vec![leaf((0..4).map(|k| 2 * k)), leaf((4..8).map(|k| 2 * k))],
+            &ArcK,
+        );
+
+        assert_eq!(n.rank(&0, false), 0);
+        assert_eq!(n.rank(&0, true), 1);
+        assert_eq!(n.rank(&7, false), 4);
+        assert_eq!(n.rank(&8, true), 5);
+        assert_eq!(n.rank(&42, false), 8);
+    }
+
+    #[test]
+    fn insert() {
+        let (ns, new) = leaf(0..MAX_WIDTH).insert(MAX_WIDTH, 0, &ArcK);
+
+        assert!(new);
+        assert_eq!(ns.len(), 2);
+
+        for n in ns {
+            assert!(n.is_balanced(0, false));
+        }
+
+        let (ns, new) = leaf(0..1).insert(0, 42, &ArcK);
+
+        assert!(!new);
+        assert_eq!(ns[0].get(&0), Some(&42));
+    }
+
+    #[test]
+    fn remove() {
+        let n = Node::branch(
+            vec![leaf(0..MIN_WIDTH), leaf(MIN_WIDTH..2 * MIN_WIDTH)],
+            &ArcK,
+        );
+        let n = n.remove(&0, &ArcK).unwrap();
+
+        assert_eq!(n.width(), 1);
+        assert_eq!(n.len(), 2 * MIN_WIDTH - 1);
+        assert!(n.remove(&0, &ArcK).is_none());
+    }
+}
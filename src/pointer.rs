@@ -0,0 +1,322 @@
+use alloc::alloc::{handle_alloc_error, Layout};
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+
+/// A kind of pointers through which data structures share their nodes.
+///
+/// Data structures keep values of pointer kinds and allocate all of their
+/// nodes through them.
+pub trait PointerKind: Clone {
+    type Pointer<T>: Clone + Deref<Target = T>;
+    /// An immutable array of elements in a node.
+    type Array<T>: Clone + Deref<Target = [T]>;
+
+    fn pointer<T>(&self, x: T) -> Self::Pointer<T>;
+    fn array<T>(&self, xs: Vec<T>) -> Self::Array<T>;
+    fn make_mut<'a, T: Clone>(&self, p: &'a mut Self::Pointer<T>) -> &'a mut T;
+    fn ptr_eq<T>(p: &Self::Pointer<T>, q: &Self::Pointer<T>) -> bool;
+    /// Returns an inner value if a pointer is the last one to it.
+    fn into_inner<T>(p: Self::Pointer<T>) -> Option<T>;
+}
+
+/// A pointer kind of atomically reference-counted pointers.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ArcK;
+
+impl PointerKind for ArcK {
+    type Pointer<T> = Arc<T>;
+    type Array<T> = Arc<[T]>;
+
+    fn pointer<T>(&self, x: T) -> Arc<T> {
+        Arc::new(x)
+    }
+
+    fn array<T>(&self, xs: Vec<T>) -> Arc<[T]> {
+        xs.into()
+    }
+
+    fn make_mut<'a, T: Clone>(&self, p: &'a mut Arc<T>) -> &'a mut T {
+        Arc::make_mut(p)
+    }
+
+    fn ptr_eq<T>(p: &Arc<T>, q: &Arc<T>) -> bool {
+        Arc::ptr_eq(p, q)
+    }
+
+    fn into_inner<T>(p: Arc<T>) -> Option<T> {
+        Arc::into_inner(p)
+    }
+}
+
+/// A pointer kind of non-atomically reference-counted pointers.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RcK;
+
+impl PointerKind for RcK {
+    type Pointer<T> = Rc<T>;
+    type Array<T> = Rc<[T]>;
+
+    fn pointer<T>(&self, x: T) -> Rc<T> {
+        Rc::new(x)
+    }
+
+    fn array<T>(&self, xs: Vec<T>) -> Rc<[T]> {
+        xs.into()
+    }
+
+    fn make_mut<'a, T: Clone>(&self, p: &'a mut Rc<T>) -> &'a mut T {
+        Rc::make_mut(p)
+    }
+
+    fn ptr_eq<T>(p: &Rc<T>, q: &Rc<T>) -> bool {
+        Rc::ptr_eq(p, q)
+    }
+
+    fn into_inner<T>(p: Rc<T>) -> Option<T> {
+        Rc::into_inner(p)
+    }
+}
+
+/// An allocator of nodes.
+///
+/// # Safety
+///
+/// Allocated memory must fit the given layout and stay valid while any
+/// pointer to it is alive.
+pub unsafe trait NodeAllocator: Clone {
+    fn allocate(&self, layout: Layout) -> NonNull<u8>;
+}
+
+/// A node allocator of the global heap.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Global;
+
+unsafe impl NodeAllocator for Global {
+    fn allocate(&self, layout: Layout) -> NonNull<u8> {
+        NonNull::new(unsafe { alloc::alloc::alloc(layout) })
+            .unwrap_or_else(|| handle_alloc_error(layout))
+    }
+}
+
+/// A pointer kind of pointers to memory of a node allocator.
+///
+/// Nodes and arrays in them are neither reference-counted nor dropped. Their
+/// memory is left to the allocator or external garbage collectors.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AllocK<A: NodeAllocator>(pub A);
+
+/// A pointer kind of pointers to leaked memory.
+pub type LeakK = AllocK<Global>;
+
+impl<A: NodeAllocator> AllocK<A> {
+    fn allocate<T>(&self, layout: Layout) -> NonNull<T> {
+        if layout.size() == 0 {
+            NonNull::dangling()
+        } else {
+            self.0.allocate(layout).cast()
+        }
+    }
+}
+
+impl<A: NodeAllocator> PointerKind for AllocK<A> {
+    type Pointer<T> = Leaked<T>;
+    type Array<T> = Leaked<[T]>;
+
+    fn pointer<T>(&self, x: T) -> Leaked<T> {
+        let p = self.allocate::<T>(Layout::new::<T>());
+
+        unsafe { p.as_ptr().write(x) };
+
+        Leaked(p)
+    }
+
+    // Elements are moved out of a vector, which frees only its buffer.
+    fn array<T>(&self, mut xs: Vec<T>) -> Leaked<[T]> {
+        let n = xs.len();
+        let p = self.allocate::<T>(Layout::array::<T>(n).expect("valid layout"));
+
+        unsafe {
+            ptr::copy_nonoverlapping(xs.as_ptr(), p.as_ptr(), n);
+            xs.set_len(0);
+        }
+
+        Leaked(NonNull::slice_from_raw_parts(p, n))
+    }
+
+    // Leaked nodes can be shared anywhere, so they are always copied.
+    fn make_mut<'a, T: Clone>(&self, p: &'a mut Leaked<T>) -> &'a mut T {
+        *p = self.pointer((**p).clone());
+        unsafe { &mut *p.0.as_ptr() }
+    }
+
+    fn ptr_eq<T>(p: &Leaked<T>, q: &Leaked<T>) -> bool {
+        p.0 == q.0
+    }
+
+    fn into_inner<T>(_: Leaked<T>) -> Option<T> {
+        None
+    }
+}
+
+/// A pointer to leaked memory.
+pub struct Leaked<T: ?Sized>(NonNull<T>);
+
+impl<T: ?Sized> Leaked<T> {
+    /// Converts a pointer into a reference with an arbitrary lifetime.
+    ///
+    /// # Safety
+    ///
+    /// The memory must stay valid for the lifetime. It holds for any lifetime
+    /// with [`LeakK`], whose memory is never freed, but not with allocators
+    /// which free their memory.
+    pub unsafe fn into_ref<'a>(self) -> &'a T {
+        &*self.0.as_ptr()
+    }
+}
+
+impl<T: ?Sized> Clone for Leaked<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Leaked<T> {}
+
+impl<T: ?Sized> Deref for Leaked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.0.as_ref() }
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for Leaked<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+unsafe impl<T: ?Sized + Sync> Send for Leaked<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for Leaked<T> {}
+
+/// A node allocator which counts allocations.
+#[cfg(test)]
+#[derive(Clone, Copy)]
+pub(crate) struct CountingAllocator<'a>(pub &'a core::sync::atomic::AtomicUsize);
+
+#[cfg(test)]
+unsafe impl NodeAllocator for CountingAllocator<'_> {
+    fn allocate(&self, layout: Layout) -> NonNull<u8> {
+        self.0.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
+        Global.allocate(layout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AllocK, ArcK, CountingAllocator, LeakK, PointerKind, RcK};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    fn make_mut<P: PointerKind>(k: P) {
+        let mut p = k.pointer(0);
+        let q = p.clone();
+
+        *k.make_mut(&mut p) = 1;
+
+        assert_eq!(*p, 1);
+        assert_eq!(*q, 0);
+        assert!(!P::ptr_eq(&p, &q));
+    }
+
+    fn array<P: PointerKind>(k: P) {
+        assert_eq!(&*k.array(vec![0, 1, 2]), &[0, 1, 2]);
+        assert_eq!(&*k.array::<usize>(vec![]), &[] as &[usize]);
+        assert_eq!(&*k.array(vec![(), ()]), &[(), ()]);
+    }
+
+    #[test]
+    fn make_mut_arc() {
+        make_mut(ArcK);
+    }
+
+    #[test]
+    fn make_mut_rc() {
+        make_mut(RcK);
+    }
+
+    #[test]
+    fn make_mut_leak() {
+        make_mut(LeakK::default());
+    }
+
+    #[test]
+    fn make_mut_alloc() {
+        make_mut(AllocK(CountingAllocator(&AtomicUsize::new(0))));
+    }
+
+    #[test]
+    fn array_arc() {
+        array(ArcK);
+    }
+
+    #[test]
+    fn array_rc() {
+        array(RcK);
+    }
+
+    #[test]
+    fn array_leak() {
+        array(LeakK::default());
+    }
+
+    #[test]
+    fn allocate() {
+        let n = AtomicUsize::new(0);
+        let k = AllocK(CountingAllocator(&n));
+
+        assert_eq!(*k.pointer(42), 42);
+        assert_eq!(&*k.array(vec![42]), &[42]);
+        assert_eq!(n.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn allocate_zero_sized() {
+        let n = AtomicUsize::new(0);
+        let k = AllocK(CountingAllocator(&n));
+
+        assert_eq!(*k.pointer(()), ());
+        assert_eq!(k.array::<usize>(vec![]).len(), 0);
+        assert_eq!(n.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn into_inner() {
+        let p = ArcK.pointer(0);
+        let q = p.clone();
+
+        assert_eq!(ArcK::into_inner(p), None);
+        assert_eq!(ArcK::into_inner(q), Some(0));
+        assert_eq!(RcK::into_inner(RcK.pointer(0)), Some(0));
+        assert_eq!(LeakK::into_inner(LeakK::default().pointer(0)), None);
+    }
+
+    #[test]
+    fn ptr_eq() {
+        let k = LeakK::default();
+        let p = k.pointer(0);
+
+        assert!(LeakK::ptr_eq(&p, &p.clone()));
+        assert!(!LeakK::ptr_eq(&p, &k.pointer(0)));
+    }
+
+    #[test]
+    fn into_ref() {
+        let x: &'static usize = unsafe { LeakK::default().pointer(42).into_ref() };
+
+        assert_eq!(*x, 42);
+    }
+}
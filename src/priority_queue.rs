@@ -0,0 +1,356 @@
+use crate::pointer::{ArcK, PointerKind};
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{self, Debug, Formatter};
+use core::iter::FromIterator;
+
+/// A persistent min-priority queue as a leftist heap.
+pub struct PriorityQueue<T, P: PointerKind = ArcK> {
+    len: usize,
+    node: Option<P::Pointer<Node<T, P>>>,
+    pointer_kind: P,
+}
+
+struct Node<T, P: PointerKind> {
+    // The length of the right spine.
+    rank: usize,
+    value: T,
+    left: Option<P::Pointer<Node<T, P>>>,
+    right: Option<P::Pointer<Node<T, P>>>,
+}
+
+impl<T, P: PointerKind> Node<T, P> {
+    fn new(x: T, a: Option<P::Pointer<Self>>, b: Option<P::Pointer<Self>>) -> Self {
+        let (a, b) = if Self::rank(&a) >= Self::rank(&b) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+
+        Self {
+            rank: Self::rank(&b) + 1,
+            value: x,
+            left: a,
+            right: b,
+        }
+    }
+
+    fn rank(n: &Option<P::Pointer<Self>>) -> usize {
+        n.as_ref().map(|n| n.rank).unwrap_or(0)
+    }
+}
+
+impl<T: Clone + Ord, P: PointerKind + Default> PriorityQueue<T, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Clone + Ord, P: PointerKind> PriorityQueue<T, P> {
+    /// Creates a queue which allocates its nodes through a pointer kind.
+    pub fn new_in(p: P) -> Self {
+        Self {
+            len: 0,
+            node: None,
+            pointer_kind: p,
+        }
+    }
+
+    pub fn push(&self, x: T) -> Self {
+        let n = Some(self.pointer_kind.pointer(Node::new(x, None, None)));
+
+        self.with_node(self.len + 1, self.merge_nodes(&self.node, &n))
+    }
+
+    pub fn pop_min(&self) -> Option<(T, Self)> {
+        let n = self.node.as_ref()?;
+
+        Some((
+            n.value.clone(),
+            self.with_node(self.len - 1, self.merge_nodes(&n.left, &n.right)),
+        ))
+    }
+
+    pub fn merge(&self, other: &Self) -> Self {
+        self.with_node(
+            self.len + other.len,
+            self.merge_nodes(&self.node, &other.node),
+        )
+    }
+
+    fn with_node(&self, len: usize, node: Option<P::Pointer<Node<T, P>>>) -> Self {
+        Self {
+            len,
+            node,
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+
+    // Recursion is bounded by the lengths of right spines which are logarithmic.
+    fn merge_nodes(
+        &self,
+        a: &Option<P::Pointer<Node<T, P>>>,
+        b: &Option<P::Pointer<Node<T, P>>>,
+    ) -> Option<P::Pointer<Node<T, P>>> {
+        match (a, b) {
+            (None, _) => b.clone(),
+            (_, None) => a.clone(),
+            (Some(m), Some(n)) => {
+                let (m, n) = if n.value < m.value { (n, m) } else { (m, n) };
+
+                Some(self.pointer_kind.pointer(Node::new(
+                    m.value.clone(),
+                    m.left.clone(),
+                    self.merge_nodes(&m.right, &Some(n.clone())),
+                )))
+            }
+        }
+    }
+}
+
+impl<T, P: PointerKind> PriorityQueue<T, P> {
+    pub fn peek_min(&self) -> Option<&T> {
+        self.node.as_ref().map(|n| &n.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T, P: PointerKind> Drop for PriorityQueue<T, P> {
+    // Nodes are dropped one by one so that long left spines do not overflow stacks.
+    fn drop(&mut self) {
+        let mut ns: Vec<_> = self.node.take().into_iter().collect();
+
+        while let Some(n) = ns.pop() {
+            if let Some(mut n) = P::into_inner(n) {
+                ns.extend(n.left.take());
+                ns.extend(n.right.take());
+            }
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for PriorityQueue<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            len: self.len,
+            node: self.node.clone(),
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<T: Debug + Ord, P: PointerKind> Debug for PriorityQueue<T, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: Ord, P: PointerKind> PartialEq for PriorityQueue<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.into_iter().eq(other)
+    }
+}
+
+impl<T: Ord, P: PointerKind> Eq for PriorityQueue<T, P> {}
+
+impl<T, P: PointerKind + Default> Default for PriorityQueue<T, P> {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            node: None,
+            pointer_kind: Default::default(),
+        }
+    }
+}
+
+impl<T: Clone + Ord, P: PointerKind + Default> FromIterator<T> for PriorityQueue<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(i: I) -> Self {
+        let mut q = Self::default();
+
+        for x in i {
+            q = q.push(x);
+        }
+
+        q
+    }
+}
+
+/// An iterator over elements in ascending order.
+pub struct PriorityQueueIterator<'a, T: Ord, P: PointerKind = ArcK> {
+    nodes: BinaryHeap<MinNode<'a, T, P>>,
+}
+
+struct MinNode<'a, T, P: PointerKind>(&'a Node<T, P>);
+
+impl<'a, T: Ord, P: PointerKind> PartialEq for MinNode<'a, T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.value == other.0.value
+    }
+}
+
+impl<'a, T: Ord, P: PointerKind> Eq for MinNode<'a, T, P> {}
+
+impl<'a, T: Ord, P: PointerKind> PartialOrd for MinNode<'a, T, P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, T: Ord, P: PointerKind> Ord for MinNode<'a, T, P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.value.cmp(&self.0.value)
+    }
+}
+
+impl<'a, T: Ord, P: PointerKind> Iterator for PriorityQueueIterator<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let MinNode(n) = self.nodes.pop()?;
+
+        for c in n.left.iter().chain(&n.right) {
+            self.nodes.push(MinNode(c));
+        }
+
+        Some(&n.value)
+    }
+}
+
+impl<'a, T: Ord, P: PointerKind> IntoIterator for &'a PriorityQueue<T, P> {
+    type IntoIter = PriorityQueueIterator<'a, T, P>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        PriorityQueueIterator {
+            nodes: self.node.iter().map(|n| MinNode(&**n)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PriorityQueue;
+    use crate::pointer::RcK;
+    use rand::random;
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[test]
+    fn new() {
+        PriorityQueue::<usize>::new();
+    }
+
+    #[test]
+    fn push() {
+        let q = PriorityQueue::<_>::new();
+
+        assert_eq!(q.len(), 0);
+        assert_eq!(q.peek_min(), None);
+        assert_eq!(q.push(1).len(), 1);
+        assert_eq!(q.push(1).push(0).peek_min(), Some(&0));
+        assert_eq!(q.push(0).push(1).peek_min(), Some(&0));
+        assert_eq!(q.push(0).push(0).len(), 2);
+    }
+
+    #[test]
+    fn pop_min() {
+        let q = PriorityQueue::<_>::new();
+
+        assert_eq!(q.pop_min(), None);
+        assert_eq!(q.push(0).pop_min(), Some((0, q.clone())));
+        assert_eq!(q.push(1).push(0).pop_min(), Some((0, q.push(1))));
+    }
+
+    #[test]
+    fn merge() {
+        let q: PriorityQueue<usize> = (0..NUM_ITERATIONS).step_by(2).collect();
+        let r: PriorityQueue<usize> = (1..NUM_ITERATIONS).step_by(2).collect();
+
+        assert_eq!(q.merge(&r).len(), NUM_ITERATIONS);
+        assert!(q.merge(&r).into_iter().copied().eq(0..NUM_ITERATIONS));
+        assert_eq!(q.merge(&PriorityQueue::new()), q);
+    }
+
+    #[test]
+    fn push_pop_many() {
+        let mut q: PriorityQueue<u16> = PriorityQueue::new();
+        let mut h = BinaryHeap::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            if random() {
+                let x = random();
+                q = q.push(x);
+                h.push(Reverse(x));
+            } else if let Some((x, qq)) = q.pop_min() {
+                q = qq;
+                assert_eq!(Some(Reverse(x)), h.pop());
+            }
+
+            assert_eq!(q.len(), h.len());
+            assert_eq!(q.peek_min(), h.peek().map(|Reverse(x)| x));
+        }
+
+        let mut xs: Vec<_> = h.into_iter().map(|Reverse(x)| x).collect();
+        xs.sort();
+
+        assert_eq!(q.into_iter().copied().collect::<Vec<_>>(), xs);
+    }
+
+    #[test]
+    fn equality() {
+        let q: PriorityQueue<usize> = (0..NUM_ITERATIONS).collect();
+
+        assert_eq!(q, (0..NUM_ITERATIONS).rev().collect());
+        assert_ne!(q, q.pop_min().unwrap().1);
+    }
+
+    #[test]
+    fn drop_long_spine() {
+        let mut q = PriorityQueue::<_>::new();
+
+        for i in (0..1 << 20).rev() {
+            q = q.push(i);
+        }
+    }
+
+    #[test]
+    fn drop_long_rc_spine() {
+        let mut q = PriorityQueue::<usize, RcK>::default();
+
+        for i in (0..1 << 20).rev() {
+            q = q.push(i);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod bench {
+    use super::PriorityQueue;
+    use test::Bencher;
+
+    #[bench]
+    fn bench_push_pop_1000(b: &mut Bencher) {
+        b.iter(|| {
+            let mut q = PriorityQueue::<_>::new();
+
+            for i in 0..1000 {
+                q = q.push((i * 7919) % 1000);
+            }
+
+            while let Some((_, qq)) = q.pop_min() {
+                q = qq;
+            }
+        });
+    }
+}
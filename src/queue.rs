@@ -0,0 +1,477 @@
+use crate::list::{List, ListIterator, ListReverseIterator};
+use crate::pointer::{ArcK, PointerKind};
+use core::fmt::{self, Debug, Formatter};
+use core::iter::{FromIterator, Skip};
+
+/// A persistent FIFO queue as a real-time queue of Hood and Melville.
+///
+/// A back list is reversed onto a front list when it gets longer than the
+/// front one. The reversal is done incrementally by a few steps in every
+/// operation so that all operations take constant time in the worst case
+/// even on old versions.
+pub struct Queue<T, P: PointerKind = ArcK> {
+    // The number of elements in the front list and ones being rotated into it.
+    front_len: usize,
+    front: List<T, P>,
+    rotation: Rotation<T, P>,
+    back: List<T, P>,
+}
+
+// A state of incremental reversal of a back list appended to a front list.
+enum Rotation<T, P: PointerKind> {
+    Idle,
+    // The number of valid elements in a reversed front list, the rests of the
+    // front and back lists, and their reversed prefixes.
+    Reversing {
+        valid: usize,
+        front: List<T, P>,
+        reversed_front: List<T, P>,
+        back: List<T, P>,
+        reversed_back: List<T, P>,
+    },
+    // Valid elements in the reversed front list are moved onto the reversed
+    // back list.
+    Appending {
+        valid: usize,
+        reversed_front: List<T, P>,
+        front: List<T, P>,
+    },
+    Done(List<T, P>),
+}
+
+impl<T: Clone, P: PointerKind> Rotation<T, P> {
+    fn step(self) -> Self {
+        match self {
+            Rotation::Reversing {
+                valid,
+                front,
+                reversed_front,
+                back,
+                reversed_back,
+            } => match (front.head(), back.head()) {
+                (Some(x), Some(y)) => Rotation::Reversing {
+                    valid: valid + 1,
+                    reversed_front: reversed_front.cons(x.clone()),
+                    reversed_back: reversed_back.cons(y.clone()),
+                    front: front.tail().unwrap(),
+                    back: back.tail().unwrap(),
+                },
+                // A back list is longer than a front list by one.
+                (None, Some(y)) => Rotation::Appending {
+                    valid,
+                    reversed_front,
+                    front: reversed_back.cons(y.clone()),
+                },
+                _ => unreachable!(),
+            },
+            Rotation::Appending {
+                valid: 0, front, ..
+            } => Rotation::Done(front),
+            Rotation::Appending {
+                valid,
+                reversed_front,
+                front,
+            } => Rotation::Appending {
+                valid: valid - 1,
+                front: front.cons(reversed_front.head().unwrap().clone()),
+                reversed_front: reversed_front.tail().unwrap(),
+            },
+            r => r,
+        }
+    }
+
+    // Invalidates the first element of a front list which is popped.
+    fn invalidate(self) -> Self {
+        match self {
+            Rotation::Reversing {
+                valid,
+                front,
+                reversed_front,
+                back,
+                reversed_back,
+            } => Rotation::Reversing {
+                valid: valid - 1,
+                front,
+                reversed_front,
+                back,
+                reversed_back,
+            },
+            Rotation::Appending {
+                valid: 0, front, ..
+            } => Rotation::Done(front.tail().unwrap()),
+            Rotation::Appending {
+                valid,
+                reversed_front,
+                front,
+            } => Rotation::Appending {
+                valid: valid - 1,
+                reversed_front,
+                front,
+            },
+            r => r,
+        }
+    }
+}
+
+impl<T: Clone, P: PointerKind + Default> Queue<T, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Clone, P: PointerKind> Queue<T, P> {
+    pub fn push_back(&self, x: T) -> Self {
+        Self::check(
+            self.front_len,
+            self.front.clone(),
+            self.rotation.clone(),
+            self.back.cons(x),
+        )
+    }
+
+    pub fn pop_front(&self) -> Option<Self> {
+        let front = self.front.tail()?;
+
+        Some(Self::check(
+            self.front_len - 1,
+            front,
+            self.rotation.clone().invalidate(),
+            self.back.clone(),
+        ))
+    }
+
+    fn check(
+        front_len: usize,
+        front: List<T, P>,
+        rotation: Rotation<T, P>,
+        back: List<T, P>,
+    ) -> Self {
+        let (front_len, rotation, back) = if back.len() <= front_len {
+            (front_len, rotation, back)
+        } else {
+            let p = back.pointer_kind().clone();
+
+            (
+                front_len + back.len(),
+                Rotation::Reversing {
+                    valid: 0,
+                    front: front.clone(),
+                    reversed_front: List::new_in(p.clone()),
+                    back,
+                    reversed_back: List::new_in(p.clone()),
+                },
+                List::new_in(p),
+            )
+        };
+
+        match rotation.step().step() {
+            Rotation::Done(front) => Self {
+                front_len,
+                front,
+                rotation: Rotation::Idle,
+                back,
+            },
+            rotation => Self {
+                front_len,
+                front,
+                rotation,
+                back,
+            },
+        }
+    }
+}
+
+impl<T, P: PointerKind> Queue<T, P> {
+    /// Creates a queue which allocates its nodes through a pointer kind.
+    pub fn new_in(p: P) -> Self {
+        Self {
+            front_len: 0,
+            front: List::new_in(p.clone()),
+            rotation: Rotation::Idle,
+            back: List::new_in(p),
+        }
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.front.head()
+    }
+
+    pub fn len(&self) -> usize {
+        self.front_len + self.back.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.front_len == 0
+    }
+}
+
+impl<T, P: PointerKind> Clone for Queue<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            front_len: self.front_len,
+            front: self.front.clone(),
+            rotation: self.rotation.clone(),
+            back: self.back.clone(),
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for Rotation<T, P> {
+    fn clone(&self) -> Self {
+        match self {
+            Rotation::Idle => Rotation::Idle,
+            Rotation::Reversing {
+                valid,
+                front,
+                reversed_front,
+                back,
+                reversed_back,
+            } => Rotation::Reversing {
+                valid: *valid,
+                front: front.clone(),
+                reversed_front: reversed_front.clone(),
+                back: back.clone(),
+                reversed_back: reversed_back.clone(),
+            },
+            Rotation::Appending {
+                valid,
+                reversed_front,
+                front,
+            } => Rotation::Appending {
+                valid: *valid,
+                reversed_front: reversed_front.clone(),
+                front: front.clone(),
+            },
+            Rotation::Done(front) => Rotation::Done(front.clone()),
+        }
+    }
+}
+
+impl<T: Debug, P: PointerKind> Debug for Queue<T, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq, P: PointerKind> PartialEq for Queue<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.into_iter().eq(other)
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for Queue<T, P> {}
+
+impl<T, P: PointerKind + Default> Default for Queue<T, P> {
+    fn default() -> Self {
+        Self::new_in(Default::default())
+    }
+}
+
+impl<T, P: PointerKind + Default> FromIterator<T> for Queue<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(i: I) -> Self {
+        let front: List<T, P> = i.into_iter().collect();
+
+        Self {
+            front_len: front.len(),
+            front,
+            rotation: Rotation::Idle,
+            back: List::default(),
+        }
+    }
+}
+
+/// An iterator over elements from front to back.
+///
+/// Back lists are iterated over in reverse without being copied, which takes
+/// logarithmic time per element.
+pub struct QueueIterator<'a, T, P: PointerKind = ArcK> {
+    front: ListIterator<'a, T, P>,
+    // Parts of a back list being rotated into a front list.
+    reversing_back: Option<ListReverseIterator<'a, T, P>>,
+    reversed_back: Option<Skip<ListIterator<'a, T, P>>>,
+    back: ListReverseIterator<'a, T, P>,
+}
+
+impl<'a, T, P: PointerKind> Iterator for QueueIterator<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.front
+            .next()
+            .or_else(|| self.reversing_back.as_mut()?.next())
+            .or_else(|| self.reversed_back.as_mut()?.next())
+            .or_else(|| self.back.next())
+    }
+}
+
+impl<'a, T, P: PointerKind> IntoIterator for &'a Queue<T, P> {
+    type IntoIter = QueueIterator<'a, T, P>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let (reversing_back, reversed_back) = match &self.rotation {
+            Rotation::Reversing {
+                back,
+                reversed_back,
+                ..
+            } => (Some(back), Some(reversed_back)),
+            Rotation::Appending { front, .. } => (None, Some(front)),
+            Rotation::Idle | Rotation::Done(_) => (None, None),
+        };
+        // Elements of an old back list follow valid ones in a front list.
+        let n = self.front_len - self.front.len() - reversing_back.map_or(0, List::len);
+
+        QueueIterator {
+            front: self.front.into_iter(),
+            reversing_back: reversing_back.map(List::iter_rev),
+            reversed_back: reversed_back.map(|l| l.into_iter().skip(l.len() - n)),
+            back: self.back.iter_rev(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Queue;
+    use crate::pointer::{AllocK, CountingAllocator};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use rand::random;
+    use std::collections::VecDeque;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    #[test]
+    fn new() {
+        Queue::<usize>::new();
+    }
+
+    #[test]
+    fn push_back() {
+        let q = Queue::<_>::new();
+
+        assert_eq!(q.len(), 0);
+        assert_eq!(q.peek(), None);
+        assert_eq!(q.push_back(0).len(), 1);
+        assert_eq!(q.push_back(0).peek(), Some(&0));
+        assert_eq!(q.push_back(0).push_back(1).peek(), Some(&0));
+    }
+
+    #[test]
+    fn pop_front() {
+        let q = Queue::<_>::new();
+
+        assert_eq!(q.pop_front(), None);
+        assert_eq!(q.push_back(0).pop_front(), Some(q.clone()));
+        assert_eq!(
+            q.push_back(0).push_back(1).pop_front(),
+            Some(q.push_back(1))
+        );
+    }
+
+    #[test]
+    fn push_pop_many() {
+        let mut q = Queue::<_>::new();
+        let mut d = VecDeque::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            if random() {
+                let x: usize = random();
+
+                q = q.push_back(x);
+                d.push_back(x);
+            } else {
+                q = q.pop_front().unwrap_or(q);
+                d.pop_front();
+            }
+
+            assert_eq!(q.len(), d.len());
+            assert_eq!(q.peek(), d.front());
+        }
+
+        assert!(q.into_iter().eq(&d));
+    }
+
+    #[test]
+    fn pop_front_from_old_version() {
+        let n = AtomicUsize::new(0);
+        let mut q = Queue::new_in(AllocK(CountingAllocator(&n)));
+
+        for i in 0..NUM_ITERATIONS {
+            let qq = q.push_back(i);
+
+            for _ in 0..8 {
+                n.store(0, Ordering::SeqCst);
+
+                assert_eq!(q.pop_front().map(|q| q.len()), i.checked_sub(1));
+                // Rotations move at most two elements in each operation.
+                assert!(n.load(Ordering::SeqCst) <= 4);
+            }
+
+            q = qq;
+        }
+
+        for i in 0..NUM_ITERATIONS {
+            assert_eq!(q.peek(), Some(&i));
+            q = q.pop_front().unwrap();
+        }
+    }
+
+    #[test]
+    fn iterate_while_rotating() {
+        let mut q = Queue::<_>::new();
+        let mut d = VecDeque::new();
+
+        for _ in 0..256 {
+            if random() && random() {
+                q = q.pop_front().unwrap_or(q);
+                d.pop_front();
+            } else {
+                let x: usize = random();
+
+                q = q.push_back(x);
+                d.push_back(x);
+            }
+
+            assert!(q.into_iter().eq(&d));
+        }
+    }
+
+    #[test]
+    fn persistence() {
+        let q: Queue<usize> = (0..NUM_ITERATIONS).collect();
+        let qq = q.pop_front().unwrap().push_back(NUM_ITERATIONS);
+
+        assert!(q.into_iter().copied().eq(0..NUM_ITERATIONS));
+        assert!(qq.into_iter().copied().eq(1..NUM_ITERATIONS + 1));
+    }
+
+    #[test]
+    fn equality() {
+        let q = Queue::<_>::new().push_back(0).push_back(1).push_back(2);
+        let qq = Queue::new().push_back(42).push_back(0).push_back(1);
+
+        assert_eq!(q, qq.pop_front().unwrap().push_back(2));
+        assert_ne!(q, qq);
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod bench {
+    use super::Queue;
+    use test::Bencher;
+
+    #[bench]
+    fn bench_push_pop_1000(b: &mut Bencher) {
+        b.iter(|| {
+            let mut q = Queue::<_>::new();
+
+            for i in 0..1000 {
+                q = q.push_back(i);
+            }
+
+            while let Some(qq) = q.pop_front() {
+                q = qq;
+            }
+        });
+    }
+}
@@ -0,0 +1,342 @@
+mod node;
+
+use crate::pointer::{ArcK, PointerKind};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use core::iter::FromIterator;
+use node::Node;
+
+/// A persistent map from byte strings as a radix trie.
+pub struct TrieMap<V, P: PointerKind = ArcK> {
+    root: P::Pointer<Node<V, P>>,
+    pointer_kind: P,
+}
+
+impl<V: Clone, P: PointerKind + Default> TrieMap<V, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<V: Clone, P: PointerKind> TrieMap<V, P> {
+    pub fn insert<Q: ?Sized + AsRef<[u8]>>(&self, k: &Q, v: V) -> Self {
+        let (n, _) = self.root.insert(k.as_ref(), v, &self.pointer_kind);
+        self.node(n)
+    }
+
+    pub fn remove<Q: ?Sized + AsRef<[u8]>>(&self, k: &Q) -> Option<Self> {
+        self.root
+            .remove(k.as_ref(), &self.pointer_kind)
+            .map(|n| self.node(n))
+    }
+
+    /// Removes all keys with a prefix.
+    pub fn remove_prefix<Q: ?Sized + AsRef<[u8]>>(&self, k: &Q) -> Option<Self> {
+        self.root
+            .remove_prefix(k.as_ref(), &self.pointer_kind)
+            .map(|n| self.node(n))
+    }
+
+    fn node(&self, n: Node<V, P>) -> Self {
+        Self {
+            root: self.pointer_kind.pointer(n),
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<V, P: PointerKind> TrieMap<V, P> {
+    /// Creates a map which allocates its nodes through a pointer kind.
+    pub fn new_in(p: P) -> Self {
+        Self {
+            root: p.pointer(Node::new(&p)),
+            pointer_kind: p,
+        }
+    }
+
+    pub fn get<Q: ?Sized + AsRef<[u8]>>(&self, k: &Q) -> Option<&V> {
+        self.root.get(k.as_ref())
+    }
+
+    /// Returns the longest key which is a prefix of a given key, and its value.
+    pub fn longest_prefix_match<'a, Q: ?Sized + AsRef<[u8]>>(
+        &self,
+        k: &'a Q,
+    ) -> Option<(&'a [u8], &V)> {
+        let k = k.as_ref();
+        let (i, v) = self.root.longest_prefix_match(k)?;
+        Some((&k[..i], v))
+    }
+
+    /// Iterates over keys with a prefix in lexicographic order.
+    pub fn iter_prefix<Q: ?Sized + AsRef<[u8]>>(&self, k: &Q) -> TrieMapIterator<'_, V, P> {
+        let k = k.as_ref();
+
+        match self.root.find_prefix(k) {
+            Some((n, i)) => TrieMapIterator::new(k[..i].to_vec(), n),
+            None => TrieMapIterator {
+                key: vec![],
+                nodes: vec![],
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn pointer_kind(&self) -> &P {
+        &self.pointer_kind
+    }
+}
+
+impl<V, P: PointerKind> Clone for TrieMap<V, P> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<V: Debug, P: PointerKind> Debug for TrieMap<V, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self).finish()
+    }
+}
+
+impl<V: PartialEq, P: PointerKind> PartialEq for TrieMap<V, P> {
+    fn eq(&self, other: &Self) -> bool {
+        P::ptr_eq(&self.root, &other.root)
+            || self.len() == other.len() && self.into_iter().eq(other)
+    }
+}
+
+impl<V: Eq, P: PointerKind> Eq for TrieMap<V, P> {}
+
+impl<V, P: PointerKind + Default> Default for TrieMap<V, P> {
+    fn default() -> Self {
+        Self::new_in(Default::default())
+    }
+}
+
+impl<K: AsRef<[u8]>, V: Clone, P: PointerKind + Default> FromIterator<(K, V)> for TrieMap<V, P> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(i: I) -> Self {
+        let mut m = Self::default();
+
+        for (k, v) in i {
+            m = m.insert(&k, v);
+        }
+
+        m
+    }
+}
+
+pub struct TrieMapIterator<'a, V, P: PointerKind = ArcK> {
+    key: Vec<u8>,
+    // Nodes are paired with lengths of keys before their prefixes.
+    nodes: Vec<(&'a Node<V, P>, usize)>,
+}
+
+impl<'a, V, P: PointerKind> TrieMapIterator<'a, V, P> {
+    fn new(key: Vec<u8>, n: &'a Node<V, P>) -> Self {
+        let i = key.len();
+
+        Self {
+            key,
+            nodes: vec![(n, i)],
+        }
+    }
+}
+
+impl<'a, V, P: PointerKind> Iterator for TrieMapIterator<'a, V, P> {
+    type Item = (Vec<u8>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((n, i)) = self.nodes.pop() {
+            self.key.truncate(i);
+            self.key.extend_from_slice(n.prefix());
+
+            for c in n.children().iter().rev() {
+                self.nodes.push((c, self.key.len()));
+            }
+
+            if let Some(v) = n.value() {
+                return Some((self.key.clone(), v));
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, V, P: PointerKind> IntoIterator for &'a TrieMap<V, P> {
+    type IntoIter = TrieMapIterator<'a, V, P>;
+    type Item = (Vec<u8>, &'a V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        TrieMapIterator::new(vec![], &self.root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TrieMap;
+    use rand::random;
+    use std::collections::BTreeMap;
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    fn random_key() -> Vec<u8> {
+        (0..random::<usize>() % 6)
+            .map(|_| b'a' + random::<u8>() % 3)
+            .collect()
+    }
+
+    #[test]
+    fn new() {
+        TrieMap::<usize>::new();
+    }
+
+    #[test]
+    fn insert() {
+        let m = TrieMap::<_>::new();
+
+        assert_eq!(m.len(), 0);
+        assert_eq!(m.insert("foo", 0).len(), 1);
+        assert_eq!(m.insert("foo", 0).insert("foo", 1).len(), 1);
+        assert_eq!(m.insert("foo", 0).insert("fo", 1).len(), 2);
+        assert_eq!(m.insert("", 0).len(), 1);
+        assert_eq!(m.insert("foo", 0).insert(b"foo", 1).get("foo"), Some(&1));
+    }
+
+    #[test]
+    fn remove() {
+        let m = TrieMap::<_>::new();
+
+        assert_eq!(m.insert("foo", 0).remove("foo"), Some(m.clone()));
+        assert_eq!(m.insert("foo", 0).remove("fo"), None);
+        assert_eq!(m.insert("foo", 0).remove("fooo"), None);
+        assert_eq!(
+            m.insert("foo", 0).insert("bar", 0).remove("bar"),
+            Some(m.insert("foo", 0))
+        );
+    }
+
+    #[test]
+    fn remove_prefix() {
+        let m = TrieMap::<_>::new()
+            .insert("foo", 0)
+            .insert("foobar", 1)
+            .insert("fox", 2);
+
+        assert_eq!(
+            m.remove_prefix("foo"),
+            Some(TrieMap::new().insert("fox", 2))
+        );
+        assert_eq!(m.remove_prefix("fo"), Some(TrieMap::new()));
+        assert_eq!(m.remove_prefix("foob"), Some(m.remove("foobar").unwrap()));
+        assert_eq!(m.remove_prefix(""), Some(TrieMap::new()));
+        assert_eq!(m.remove_prefix("bar"), None);
+        assert_eq!(m.remove_prefix("foobaz"), None);
+    }
+
+    #[test]
+    fn iter_prefix() {
+        let m = TrieMap::<_>::new()
+            .insert("foo", 0)
+            .insert("foobar", 1)
+            .insert("fox", 2);
+
+        assert_eq!(
+            m.iter_prefix("foo").collect::<Vec<_>>(),
+            vec![(b"foo".to_vec(), &0), (b"foobar".to_vec(), &1)]
+        );
+        assert_eq!(
+            m.iter_prefix("fooba").collect::<Vec<_>>(),
+            vec![(b"foobar".to_vec(), &1)]
+        );
+        assert_eq!(m.iter_prefix("").count(), 3);
+        assert_eq!(m.iter_prefix("bar").count(), 0);
+    }
+
+    #[test]
+    fn longest_prefix_match() {
+        let m = TrieMap::<_>::new().insert("/", 0).insert("/usr/", 1);
+
+        assert_eq!(
+            m.longest_prefix_match("/usr/bin"),
+            Some((&b"/usr/"[..], &1))
+        );
+        assert_eq!(m.longest_prefix_match("/us"), Some((&b"/"[..], &0)));
+        assert_eq!(m.longest_prefix_match("usr"), None);
+    }
+
+    #[test]
+    fn insert_remove_many() {
+        let mut m: TrieMap<usize> = TrieMap::new();
+        let mut n = BTreeMap::new();
+
+        for i in 0..NUM_ITERATIONS {
+            let k = random_key();
+
+            match random::<u8>() % 4 {
+                0 => {
+                    m = m.remove(&k).unwrap_or(m);
+                    n.remove(&k);
+                }
+                1 => {
+                    m = m.remove_prefix(&k).unwrap_or(m);
+                    n.retain(|kk: &Vec<u8>, _| !kk.starts_with(&k));
+                }
+                _ => {
+                    m = m.insert(&k, i);
+                    n.insert(k.clone(), i);
+                }
+            }
+
+            assert_eq!(m.len(), n.len());
+            assert_eq!(m.get(&k), n.get(&k));
+            assert!(m.root.is_normal(true));
+        }
+
+        for _ in 0..NUM_ITERATIONS {
+            let k = random_key();
+
+            assert_eq!(
+                m.iter_prefix(&k).collect::<Vec<_>>(),
+                n.iter()
+                    .filter(|(kk, _)| kk.starts_with(&k))
+                    .map(|(kk, v)| (kk.clone(), v))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                m.longest_prefix_match(&k),
+                n.iter()
+                    .rev()
+                    .find(|(kk, _)| k.starts_with(kk))
+                    .map(|(kk, v)| (&k[..kk.len()], v))
+            );
+        }
+
+        assert_eq!(
+            m.into_iter().collect::<Vec<_>>(),
+            n.iter().map(|(k, v)| (k.clone(), v)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn from_iterator() {
+        assert_eq!(
+            vec![("foo", 0), ("bar", 1), ("foo", 2)]
+                .into_iter()
+                .collect::<TrieMap<_>>(),
+            TrieMap::new().insert("bar", 1).insert("foo", 2)
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use crate::pointer::PointerKind;
+use alloc::vec::Vec;
+
+pub struct Node<V, P: PointerKind> {
+    prefix: P::Array<u8>,
+    value: Option<V>,
+    // The number of values in a subtree.
+    len: usize,
+    // Children are sorted by the first bytes of their non-empty prefixes.
+    children: P::Array<P::Pointer<Node<V, P>>>,
+}
+
+impl<V, P: PointerKind> Node<V, P> {
+    pub fn new(p: &P) -> Self {
+        Self::branch(vec![], vec![], p)
+    }
+
+    fn leaf(prefix: Vec<u8>, v: V, p: &P) -> Self {
+        Self {
+            prefix: p.array(prefix),
+            value: Some(v),
+            len: 1,
+            children: p.array(vec![]),
+        }
+    }
+
+    fn branch(prefix: Vec<u8>, children: Vec<P::Pointer<Self>>, p: &P) -> Self {
+        Self {
+            prefix: p.array(prefix),
+            value: None,
+            len: children.iter().map(|c| c.len).sum(),
+            children: p.array(children),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    pub fn value(&self) -> Option<&V> {
+        self.value.as_ref()
+    }
+
+    pub fn children(&self) -> &[P::Pointer<Self>] {
+        &self.children
+    }
+
+    // Keys passed to nodes are relative to the ends of their prefixes.
+    pub fn get(&self, k: &[u8]) -> Option<&V> {
+        if k.is_empty() {
+            return self.value.as_ref();
+        }
+
+        let c = &self.children[self.child_index(k[0]).ok()?];
+        c.get(k.strip_prefix(&*c.prefix)?)
+    }
+
+    /// Finds a node whose subtree has all keys with a prefix, returning the
+    /// number of bytes of the prefix before the node.
+    pub fn find_prefix(&self, k: &[u8]) -> Option<(&Self, usize)> {
+        let mut n = self;
+        let mut i = 0;
+
+        while i < k.len() {
+            let c = &n.children[n.child_index(k[i]).ok()?];
+
+            if c.prefix.starts_with(&k[i..]) {
+                return Some((c, i));
+            } else if !k[i..].starts_with(&c.prefix) {
+                return None;
+            }
+
+            i += c.prefix.len();
+            n = c;
+        }
+
+        Some((n, i - n.prefix.len()))
+    }
+
+    /// Returns the length of the longest key which is a prefix of a given key.
+    pub fn longest_prefix_match(&self, k: &[u8]) -> Option<(usize, &V)> {
+        let mut n = self;
+        let mut i = 0;
+        let mut m = n.value.as_ref().map(|v| (i, v));
+
+        while i < k.len() {
+            let c = match n.child_index(k[i]) {
+                Ok(j) => &n.children[j],
+                Err(_) => break,
+            };
+
+            if !k[i..].starts_with(&c.prefix) {
+                break;
+            }
+
+            i += c.prefix.len();
+            n = c;
+
+            if let Some(v) = &n.value {
+                m = Some((i, v));
+            }
+        }
+
+        m
+    }
+
+    fn child_index(&self, b: u8) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&b, |c| c.prefix[0])
+    }
+
+    #[cfg(test)]
+    pub fn is_normal(&self, root: bool) -> bool {
+        self.len == self.value.iter().count() + self.children.iter().map(|c| c.len).sum::<usize>()
+            && (root || !self.prefix.is_empty())
+            && (root || self.value.is_some() || self.children.len() >= 2)
+            && self
+                .children
+                .windows(2)
+                .all(|cs| cs[0].prefix[0] < cs[1].prefix[0])
+            && self.children.iter().all(|c| c.is_normal(false))
+    }
+}
+
+impl<V: Clone, P: PointerKind> Node<V, P> {
+    pub fn insert(&self, k: &[u8], v: V, p: &P) -> (Self, bool) {
+        let mut n = self.clone();
+
+        let new = if k.is_empty() {
+            n.value.replace(v).is_none()
+        } else {
+            let mut cs = self.children.to_vec();
+
+            let new = match self.child_index(k[0]) {
+                Ok(j) => {
+                    let c = &self.children[j];
+                    let l = common_prefix_len(&c.prefix, k);
+
+                    let (c, new) = if l == c.prefix.len() {
+                        c.insert(&k[l..], v, p)
+                    } else {
+                        let mut d = (**c).clone();
+                        d.prefix = p.array(c.prefix[l..].to_vec());
+                        Self::branch(c.prefix[..l].to_vec(), vec![p.pointer(d)], p).insert(
+                            &k[l..],
+                            v,
+                            p,
+                        )
+                    };
+
+                    cs[j] = p.pointer(c);
+                    new
+                }
+                Err(j) => {
+                    cs.insert(j, p.pointer(Self::leaf(k.to_vec(), v, p)));
+                    true
+                }
+            };
+
+            n.children = p.array(cs);
+            new
+        };
+
+        if new {
+            n.len += 1;
+        }
+
+        (n, new)
+    }
+
+    pub fn remove(&self, k: &[u8], p: &P) -> Option<Self> {
+        if k.is_empty() {
+            self.value.as_ref()?;
+
+            let mut n = self.clone();
+            n.value = None;
+            n.len -= 1;
+            return Some(n);
+        }
+
+        let j = self.child_index(k[0]).ok()?;
+        let c = &self.children[j];
+        let c = c.remove(k.strip_prefix(&*c.prefix)?, p)?;
+
+        Some(self.set_child(j, Some(c), p))
+    }
+
+    /// Removes all keys with a prefix.
+    pub fn remove_prefix(&self, k: &[u8], p: &P) -> Option<Self> {
+        if k.is_empty() {
+            return if self.len == 0 {
+                None
+            } else {
+                Some(Self::branch(self.prefix.to_vec(), vec![], p))
+            };
+        }
+
+        let j = self.child_index(k[0]).ok()?;
+        let c = &self.children[j];
+
+        let c = if c.prefix.starts_with(k) {
+            None
+        } else {
+            Some(c.remove_prefix(k.strip_prefix(&*c.prefix)?, p)?)
+        };
+
+        Some(self.set_child(j, c, p))
+    }
+
+    fn set_child(&self, j: usize, c: Option<Self>, p: &P) -> Self {
+        let mut n = self.clone();
+        let mut cs = self.children.to_vec();
+        n.len -= self.children[j].len;
+
+        match c.and_then(|c| c.normalize(p)) {
+            Some(c) => {
+                n.len += c.len;
+                cs[j] = p.pointer(c);
+            }
+            None => {
+                cs.remove(j);
+            }
+        }
+
+        n.children = p.array(cs);
+        n
+    }
+
+    // Removes an empty node or merges a node without a value into its only child.
+    fn normalize(self, p: &P) -> Option<Self> {
+        if self.value.is_some() || self.children.len() > 1 {
+            return Some(self);
+        }
+
+        let c = self.children.first()?;
+        let mut d = (**c).clone();
+        d.prefix = p.array([&*self.prefix, &*c.prefix].concat());
+        Some(d)
+    }
+}
+
+impl<V: Clone, P: PointerKind> Clone for Node<V, P> {
+    fn clone(&self) -> Self {
+        Self {
+            prefix: self.prefix.clone(),
+            value: self.value.clone(),
+            len: self.len,
+            children: self.children.clone(),
+        }
+    }
+}
+
+fn common_prefix_len(xs: &[u8], ys: &[u8]) -> usize {
+    xs.iter().zip(ys).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod test {
+    use super::common_prefix_len;
+    use crate::pointer::ArcK;
+
+    type Node<V> = super::Node<V, ArcK>;
+
+    #[test]
+    fn insert_split() {
+        let (n, _) = Node::new(&ArcK).insert(b"foo", 0, &ArcK);
+        let (n, _) = n.insert(b"fox", 1, &ArcK);
+
+        assert_eq!(n.children().len(), 1);
+        assert_eq!(n.children()[0].prefix(), b"fo");
+        assert_eq!(n.children()[0].children().len(), 2);
+        assert!(n.is_normal(true));
+    }
+
+    #[test]
+    fn remove_merge() {
+        let (n, _) = Node::new(&ArcK).insert(b"foo", 0, &ArcK);
+        let (n, _) = n.insert(b"fox", 1, &ArcK);
+        let n = n.remove(b"fox", &ArcK).unwrap();
+
+        assert_eq!(n.children()[0].prefix(), b"foo");
+        assert!(n.is_normal(true));
+    }
+
+    #[test]
+    fn common_prefix() {
+        assert_eq!(common_prefix_len(b"", b"foo"), 0);
+        assert_eq!(common_prefix_len(b"fox", b"foo"), 2);
+        assert_eq!(common_prefix_len(b"foo", b"foobar"), 3);
+    }
+}
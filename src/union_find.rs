@@ -0,0 +1,231 @@
+use crate::hash_map::{DefaultBuildHasher, HashMap};
+use crate::pointer::{ArcK, PointerKind};
+use core::fmt::{self, Debug, Formatter};
+use core::hash::{BuildHasher, Hash};
+
+/// A persistent disjoint-set forest.
+///
+/// Elements without parents are roots of their own sets. Path compression
+/// returns new versions so that old versions stay valid.
+pub struct UnionFind<K: Eq + Hash, S = DefaultBuildHasher, P: PointerKind = ArcK> {
+    parents: HashMap<K, K, S, P>,
+    // Only roots with non-zero ranks are stored.
+    ranks: HashMap<K, usize, S, P>,
+}
+
+impl<K: Clone + Eq + Hash, P: PointerKind + Default> UnionFind<K, DefaultBuildHasher, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: Clone + Eq + Hash, S: BuildHasher + Clone, P: PointerKind + Default> UnionFind<K, S, P> {
+    pub fn with_hasher(s: S) -> Self {
+        Self::with_hasher_in(s, Default::default())
+    }
+}
+
+impl<K: Clone + Eq + Hash, S: BuildHasher + Clone, P: PointerKind> UnionFind<K, S, P> {
+    /// Creates a union-find which allocates its nodes through a pointer kind.
+    pub fn with_hasher_in(s: S, p: P) -> Self {
+        Self {
+            parents: HashMap::with_hasher_in(s.clone(), p.clone()),
+            ranks: HashMap::with_hasher_in(s, p),
+        }
+    }
+
+    /// Finds a root of an element compressing a path to it.
+    pub fn find(&self, x: &K) -> (K, Self) {
+        let mut xs = vec![];
+        let mut r = x;
+
+        while let Some(y) = self.parents.get(r) {
+            xs.push(r);
+            r = y;
+        }
+
+        let mut parents = self.parents.clone();
+
+        // The last element already points to the root.
+        for x in xs.iter().rev().skip(1) {
+            parents = parents.insert((*x).clone(), r.clone());
+        }
+
+        (
+            r.clone(),
+            Self {
+                parents,
+                ranks: self.ranks.clone(),
+            },
+        )
+    }
+
+    /// Merges sets of two elements by rank.
+    pub fn union(&self, x: &K, y: &K) -> Self {
+        let (x, u) = self.find(x);
+        let (y, u) = u.find(y);
+
+        if x == y {
+            return u;
+        }
+
+        let (m, n) = (u.rank(&x), u.rank(&y));
+        let (x, y) = if m < n { (x, y) } else { (y, x) };
+
+        let ranks = u.ranks.remove(&x).unwrap_or(u.ranks);
+
+        Self {
+            parents: u.parents.insert(x, y.clone()),
+            ranks: if m == n {
+                ranks.insert(y, m + 1)
+            } else {
+                ranks
+            },
+        }
+    }
+}
+
+impl<K: Eq + Hash, S: BuildHasher, P: PointerKind> UnionFind<K, S, P> {
+    /// Finds a root of an element without path compression.
+    pub fn root<'a>(&'a self, mut x: &'a K) -> &'a K {
+        while let Some(y) = self.parents.get(x) {
+            x = y;
+        }
+
+        x
+    }
+
+    /// Returns true if two elements are in the same set.
+    pub fn connected(&self, x: &K, y: &K) -> bool {
+        self.root(x) == self.root(y)
+    }
+
+    fn rank(&self, x: &K) -> usize {
+        self.ranks.get(x).copied().unwrap_or(0)
+    }
+
+    #[cfg(test)]
+    fn depth(&self, x: &K) -> usize {
+        let mut x = x;
+        let mut d = 0;
+
+        while let Some(y) = self.parents.get(x) {
+            x = y;
+            d += 1;
+        }
+
+        d
+    }
+}
+
+impl<K: Eq + Hash, S: Clone, P: PointerKind> Clone for UnionFind<K, S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            parents: self.parents.clone(),
+            ranks: self.ranks.clone(),
+        }
+    }
+}
+
+impl<K: Debug + Eq + Hash, S, P: PointerKind> Debug for UnionFind<K, S, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(&self.parents).finish()
+    }
+}
+
+impl<K: Clone + Eq + Hash, S: BuildHasher + Clone + Default, P: PointerKind + Default> Default
+    for UnionFind<K, S, P>
+{
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UnionFind;
+    use rand::random;
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+    const NUM_ELEMENTS: u8 = 64;
+
+    // Finds a root in a naive forest.
+    fn root(xs: &[u8], mut x: u8) -> u8 {
+        while xs[x as usize] != x {
+            x = xs[x as usize];
+        }
+
+        x
+    }
+
+    #[test]
+    fn new() {
+        UnionFind::<usize>::new();
+    }
+
+    #[test]
+    fn find() {
+        let u = UnionFind::<_>::new();
+
+        assert_eq!(u.find(&0).0, 0);
+        assert_eq!(u.union(&0, &1).find(&0).0, u.union(&0, &1).find(&1).0);
+        assert_ne!(u.union(&0, &1).find(&0).0, 2);
+    }
+
+    #[test]
+    fn union() {
+        let u = UnionFind::<_>::new().union(&0, &1).union(&2, &3);
+
+        assert!(u.connected(&0, &1));
+        assert!(u.connected(&2, &3));
+        assert!(!u.connected(&0, &2));
+        assert!(u.union(&1, &3).connected(&0, &2));
+        assert!(u.union(&0, &0).connected(&0, &0));
+    }
+
+    #[test]
+    fn backtrack() {
+        let u = UnionFind::<_>::new().union(&0, &1);
+        let v = u.union(&1, &2);
+
+        assert!(v.connected(&0, &2));
+        assert!(!u.connected(&0, &2));
+    }
+
+    #[test]
+    fn compress_path() {
+        let u = UnionFind::<_>::new()
+            .union(&0, &1)
+            .union(&2, &3)
+            .union(&0, &2);
+        let (r, v) = u.find(&3);
+
+        assert_eq!(u.depth(&3), 2);
+        assert_eq!(v.depth(&3), 1);
+        assert_eq!(v.root(&3), &r);
+    }
+
+    #[test]
+    fn union_many() {
+        let mut u = UnionFind::<_>::new();
+        let mut xs: Vec<u8> = (0..NUM_ELEMENTS).collect();
+
+        for _ in 0..NUM_ITERATIONS {
+            let x = random::<u8>() % NUM_ELEMENTS;
+            let y = random::<u8>() % NUM_ELEMENTS;
+
+            if random() {
+                u = u.union(&x, &y);
+
+                let (r, s) = (root(&xs, x), root(&xs, y));
+                xs[r as usize] = s;
+            } else {
+                u = u.find(&x).1;
+            }
+
+            assert_eq!(u.connected(&x, &y), root(&xs, x) == root(&xs, y));
+            assert!(u.depth(&x) < 8);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Single-threaded data structures backed by non-atomically reference-counted
+//! pointers.
+
+use crate::hash_map::DefaultBuildHasher;
+use crate::pointer::RcK;
+
+/// A hash map whose nodes are shared through `Rc`.
+pub type HashMap<K, V, S = DefaultBuildHasher> = crate::HashMap<K, V, S, RcK>;
+
+/// A vector whose nodes are shared through `Rc`.
+pub type Vector<T> = crate::Vector<T, RcK>;
+
+/// An ordered map whose nodes are shared through `Rc`.
+pub type OrdMap<K, V> = crate::OrdMap<K, V, RcK>;
+
+/// A list whose nodes are shared through `Rc`.
+pub type List<T> = crate::List<T, RcK>;
+
+/// A queue whose nodes are shared through `Rc`.
+pub type Queue<T> = crate::Queue<T, RcK>;
+
+/// A bag whose nodes are shared through `Rc`.
+pub type HashBag<T, S = DefaultBuildHasher> = crate::HashBag<T, S, RcK>;
+
+/// A priority queue whose nodes are shared through `Rc`.
+pub type PriorityQueue<T> = crate::PriorityQueue<T, RcK>;
+
+/// A trie whose nodes are shared through `Rc`.
+pub type TrieMap<V> = crate::TrieMap<V, RcK>;
+
+/// A multimap whose nodes are shared through `Rc`.
+pub type HashMultiMap<K, V, S = DefaultBuildHasher> = crate::HashMultiMap<K, V, S, RcK>;
+
+/// An insertion-ordered map whose nodes are shared through `Rc`.
+pub type IndexMap<K, V, S = DefaultBuildHasher> = crate::IndexMap<K, V, S, RcK>;
+
+/// A bidirectional map whose nodes are shared through `Rc`.
+pub type BiMap<L, R, S = DefaultBuildHasher> = crate::BiMap<L, R, S, RcK>;
+
+/// A disjoint-set forest whose nodes are shared through `Rc`.
+pub type UnionFind<K, S = DefaultBuildHasher> = crate::UnionFind<K, S, RcK>;
+
+/// A directed graph whose nodes are shared through `Rc`.
+pub type Graph<N, E, S = DefaultBuildHasher> = crate::Graph<N, E, S, RcK>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn new() {
+        HashMap::<usize, usize>::new();
+        Vector::<usize>::new();
+        OrdMap::<usize, usize>::new();
+        List::<usize>::new();
+        Queue::<usize>::new();
+        HashBag::<usize>::new();
+        PriorityQueue::<usize>::new();
+        TrieMap::<usize>::new();
+        HashMultiMap::<usize, usize>::new();
+        IndexMap::<usize, usize>::new();
+        BiMap::<usize, usize>::new();
+        UnionFind::<usize>::new();
+        Graph::<usize, usize>::new();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_hash_map() {
+        let h = HashMap::new().insert(0, 1);
+
+        assert_eq!(
+            serde_json::from_str::<HashMap<usize, usize>>(&serde_json::to_string(&h).unwrap())
+                .unwrap(),
+            h
+        );
+    }
+}
@@ -0,0 +1,502 @@
+mod node;
+
+use crate::pointer::{ArcK, PointerKind};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use core::iter::FromIterator;
+use node::{Node, Tree};
+
+/// A persistent vector as a relaxed radix balanced tree.
+pub struct Vector<T, P: PointerKind = ArcK> {
+    root: P::Pointer<Node<T, P>>,
+    height: usize,
+    pointer_kind: P,
+}
+
+impl<T: Clone, P: PointerKind + Default> Vector<T, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Clone, P: PointerKind> Vector<T, P> {
+    pub fn push_back(&self, x: T) -> Self {
+        self.concat(&self.leaf(vec![x]))
+    }
+
+    pub fn push_front(&self, x: T) -> Self {
+        self.leaf(vec![x]).concat(self)
+    }
+
+    pub fn pop_back(&self) -> Option<Self> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.split_at(self.len() - 1).0)
+        }
+    }
+
+    pub fn pop_front(&self) -> Option<Self> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.split_at(1).1)
+        }
+    }
+
+    pub fn set(&self, i: usize, x: T) -> Option<Self> {
+        if i >= self.len() {
+            return None;
+        }
+
+        let p = &self.pointer_kind;
+
+        Some(self.node(p.pointer(self.root.set(i, x, p)), self.height))
+    }
+
+    pub fn concat(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        } else if other.is_empty() {
+            return self.clone();
+        }
+
+        let h = self.height.max(other.height);
+
+        self.branch(
+            Node::<T, P>::join(
+                &self.root,
+                self.height,
+                &other.root,
+                other.height,
+                &self.pointer_kind,
+            ),
+            h + 1,
+        )
+    }
+
+    /// Splits a vector into two at an index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i > len`.
+    pub fn split_at(&self, i: usize) -> (Self, Self) {
+        assert!(i <= self.len(), "index out of bounds");
+
+        if i == 0 {
+            return (self.empty(), self.clone());
+        } else if i == self.len() {
+            return (self.clone(), self.empty());
+        }
+
+        let (l, r) = Node::split_at(&self.root, self.height, i, &self.pointer_kind);
+
+        (self.tree(l), self.tree(r))
+    }
+
+    fn tree(&self, t: Tree<T, P>) -> Self {
+        match t {
+            Some((n, h)) => self.node(n, h),
+            None => self.empty(),
+        }
+    }
+
+    fn leaf(&self, xs: Vec<T>) -> Self {
+        let p = &self.pointer_kind;
+
+        self.node(p.pointer(Node::leaf(xs, p)), 0)
+    }
+
+    fn branch(&self, cs: Vec<P::Pointer<Node<T, P>>>, height: usize) -> Self {
+        let p = &self.pointer_kind;
+
+        match cs.len() {
+            0 => self.empty(),
+            1 => self.node(cs[0].clone(), height - 1),
+            _ => self.node(p.pointer(Node::branch(cs, p)), height),
+        }
+    }
+
+    fn empty(&self) -> Self {
+        Self::new_in(self.pointer_kind.clone())
+    }
+}
+
+impl<T, P: PointerKind> Vector<T, P> {
+    /// Creates a vector which allocates its nodes through a pointer kind.
+    pub fn new_in(p: P) -> Self {
+        Self {
+            root: p.pointer(Node::leaf(vec![], &p)),
+            height: 0,
+            pointer_kind: p,
+        }
+    }
+
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.root.get(i)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn pointer_kind(&self) -> &P {
+        &self.pointer_kind
+    }
+
+    fn node(&self, n: P::Pointer<Node<T, P>>, height: usize) -> Self {
+        Self {
+            root: n,
+            height,
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for Vector<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            height: self.height,
+            pointer_kind: self.pointer_kind.clone(),
+        }
+    }
+}
+
+impl<T: Debug, P: PointerKind> Debug for Vector<T, P> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq, P: PointerKind> PartialEq for Vector<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && (P::ptr_eq(&self.root, &other.root) || self.into_iter().eq(other))
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for Vector<T, P> {}
+
+impl<T, P: PointerKind + Default> Default for Vector<T, P> {
+    fn default() -> Self {
+        Self::new_in(Default::default())
+    }
+}
+
+impl<T: Clone, P: PointerKind + Default> FromIterator<T> for Vector<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(i: I) -> Self {
+        let mut v = Self::default();
+
+        for x in i {
+            v = v.push_back(x);
+        }
+
+        v
+    }
+}
+
+pub struct VectorIterator<'a, T, P: PointerKind = ArcK> {
+    nodes: Vec<(&'a Node<T, P>, usize)>,
+}
+
+impl<'a, T, P: PointerKind> Iterator for VectorIterator<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (n, i) = self.nodes.last_mut()?;
+            let n: &'a Node<T, P> = n;
+
+            match n {
+                Node::Leaf(xs) => {
+                    if let Some(x) = xs.get(*i) {
+                        *i += 1;
+                        return Some(x);
+                    }
+                }
+                Node::Branch(_, cs) => {
+                    if let Some(c) = cs.get(*i) {
+                        *i += 1;
+                        self.nodes.push((c, 0));
+                        continue;
+                    }
+                }
+            }
+
+            self.nodes.pop();
+        }
+    }
+}
+
+impl<'a, T, P: PointerKind> IntoIterator for &'a Vector<T, P> {
+    type IntoIter = VectorIterator<'a, T, P>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        VectorIterator {
+            nodes: vec![(&self.root, 0)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Vector;
+    use crate::pointer::{AllocK, CountingAllocator};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use rand::random;
+    use std::vec::Vec;
+
+    const NUM_ITERATIONS: usize = 1 << 12;
+
+    fn is_balanced<T>(v: &Vector<T>) -> bool {
+        v.root.is_balanced(v.height, true)
+    }
+
+    fn random_vector() -> (Vector<usize>, Vec<usize>) {
+        let n = random::<usize>() % NUM_ITERATIONS;
+
+        ((0..n).collect(), (0..n).collect())
+    }
+
+    #[test]
+    fn new() {
+        Vector::<usize>::new();
+    }
+
+    #[test]
+    fn push_back() {
+        let mut v = Vector::new();
+
+        for i in 0..NUM_ITERATIONS {
+            v = v.push_back(i);
+
+            assert_eq!(v.len(), i + 1);
+            assert_eq!(v.last(), Some(&i));
+            assert!(is_balanced(&v));
+        }
+
+        assert!(v.into_iter().copied().eq(0..NUM_ITERATIONS));
+    }
+
+    #[test]
+    fn push_front() {
+        let mut v = Vector::new();
+
+        for i in 0..NUM_ITERATIONS {
+            v = v.push_front(i);
+
+            assert_eq!(v.len(), i + 1);
+            assert_eq!(v.first(), Some(&i));
+            assert!(is_balanced(&v));
+        }
+
+        assert!(v.into_iter().copied().eq((0..NUM_ITERATIONS).rev()));
+    }
+
+    #[test]
+    fn pop_back() {
+        let mut v: Vector<usize> = (0..NUM_ITERATIONS).collect();
+
+        for i in (0..NUM_ITERATIONS).rev() {
+            assert_eq!(v.last(), Some(&i));
+
+            v = v.pop_back().unwrap();
+
+            assert_eq!(v.len(), i);
+            assert!(is_balanced(&v));
+        }
+
+        assert_eq!(v.pop_back(), None);
+    }
+
+    #[test]
+    fn pop_front() {
+        let mut v: Vector<usize> = (0..NUM_ITERATIONS).collect();
+
+        for i in 0..NUM_ITERATIONS {
+            assert_eq!(v.first(), Some(&i));
+
+            v = v.pop_front().unwrap();
+
+            assert!(is_balanced(&v));
+        }
+
+        assert_eq!(v.pop_front(), None);
+    }
+
+    #[test]
+    fn get() {
+        let v: Vector<usize> = (0..NUM_ITERATIONS).collect();
+
+        for i in 0..NUM_ITERATIONS {
+            assert_eq!(v.get(i), Some(&i));
+        }
+
+        assert_eq!(v.get(NUM_ITERATIONS), None);
+    }
+
+    #[test]
+    fn set() {
+        let mut v: Vector<usize> = (0..NUM_ITERATIONS).collect();
+        let mut w: Vec<usize> = (0..NUM_ITERATIONS).collect();
+
+        for _ in 0..NUM_ITERATIONS {
+            let i = random::<usize>() % NUM_ITERATIONS;
+            let x = random();
+
+            v = v.set(i, x).unwrap();
+            w[i] = x;
+
+            assert_eq!(v.get(i), Some(&x));
+        }
+
+        assert!(v.into_iter().eq(&w));
+        assert_eq!(v.set(NUM_ITERATIONS, 0), None);
+    }
+
+    #[test]
+    fn split_at() {
+        for _ in 0..64 {
+            let (v, w) = random_vector();
+            let i = random::<usize>() % (w.len() + 1);
+            let (l, r) = v.split_at(i);
+
+            assert!(is_balanced(&l));
+            assert!(is_balanced(&r));
+            assert!(l.into_iter().eq(&w[..i]));
+            assert!(r.into_iter().eq(&w[i..]));
+        }
+    }
+
+    #[test]
+    fn split_at_copies_paths() {
+        let n = AtomicUsize::new(0);
+        let mut v = Vector::new_in(AllocK(CountingAllocator(&n)));
+
+        for i in 0..NUM_ITERATIONS {
+            v = v.push_back(i);
+        }
+
+        for i in 0..NUM_ITERATIONS {
+            n.store(0, Ordering::SeqCst);
+            v.split_at(i);
+
+            // Only a few nodes are copied at each level.
+            assert!(n.load(Ordering::SeqCst) <= 12 * (v.height + 1));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_at_out_of_bounds() {
+        Vector::<usize>::new().split_at(1);
+    }
+
+    #[test]
+    fn concat() {
+        for _ in 0..64 {
+            let (v, w) = random_vector();
+            let (vv, ww) = random_vector();
+            let u = v.concat(&vv);
+
+            assert!(is_balanced(&u));
+            assert!(u.into_iter().eq(w.iter().chain(&ww)));
+        }
+    }
+
+    #[test]
+    fn split_and_concat_many() {
+        let mut v = Vector::new();
+        let mut w = Vec::new();
+
+        for _ in 0..NUM_ITERATIONS {
+            let i = random::<usize>() % (w.len() + 1);
+            let x: usize = random();
+
+            let (l, r) = v.split_at(i);
+            v = l.push_back(x).concat(&r);
+            w.insert(i, x);
+
+            assert!(is_balanced(&v));
+        }
+
+        assert!(v.into_iter().eq(&w));
+    }
+
+    #[test]
+    fn equality() {
+        let v: Vector<usize> = (0..NUM_ITERATIONS).collect();
+        let (l, r) = v.split_at(NUM_ITERATIONS / 3);
+
+        assert_eq!(l.concat(&r), v);
+        assert_ne!(l, v);
+        assert_ne!(v.set(0, 42).unwrap(), v);
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod bench {
+    use super::Vector;
+    use test::Bencher;
+
+    #[bench]
+    fn bench_push_back_1000(b: &mut Bencher) {
+        b.iter(|| {
+            let mut v = Vector::<_>::new();
+
+            for i in 0..1000 {
+                v = v.push_back(i);
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_get_1000(b: &mut Bencher) {
+        let v: Vector<usize> = (0..1000).collect();
+
+        b.iter(|| {
+            for i in 0..1000 {
+                v.get(i);
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_set_1000(b: &mut Bencher) {
+        let v: Vector<usize> = (0..1000).collect();
+
+        b.iter(|| {
+            let mut v = v.clone();
+
+            for i in 0..1000 {
+                v = v.set(i, i).unwrap();
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_split_and_concat_1000(b: &mut Bencher) {
+        let v: Vector<usize> = (0..1000).collect();
+
+        b.iter(|| {
+            for i in 0..1000 {
+                let (l, r) = v.split_at(i);
+                l.concat(&r);
+            }
+        });
+    }
+}
@@ -0,0 +1,320 @@
+use crate::hash_map::NUM_ENTRIES;
+use crate::pointer::PointerKind;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+pub const MAX_WIDTH: usize = NUM_ENTRIES;
+pub const MIN_WIDTH: usize = MAX_WIDTH / 2;
+
+// A root of a tree paired with its height, or none for an empty tree.
+pub type Tree<T, P> = Option<(<P as PointerKind>::Pointer<Node<T, P>>, usize)>;
+
+pub enum Node<T, P: PointerKind> {
+    Leaf(P::Array<T>),
+    // Children are annotated with cumulative sizes.
+    Branch(P::Array<usize>, P::Array<P::Pointer<Node<T, P>>>),
+}
+
+impl<T, P: PointerKind> Node<T, P> {
+    pub fn len(&self) -> usize {
+        match self {
+            Node::Leaf(xs) => xs.len(),
+            Node::Branch(ss, _) => ss.last().copied().unwrap_or(0),
+        }
+    }
+
+    pub fn get(&self, i: usize) -> Option<&T> {
+        match self {
+            Node::Leaf(xs) => xs.get(i),
+            Node::Branch(ss, cs) => {
+                let j = child_index(ss, i);
+                cs.get(j)?.get(i - offset(ss, j))
+            }
+        }
+    }
+
+    pub fn children(&self) -> &[P::Pointer<Node<T, P>>] {
+        match self {
+            Node::Leaf(_) => unreachable!(),
+            Node::Branch(_, cs) => cs,
+        }
+    }
+
+    pub fn leaf(xs: Vec<T>, p: &P) -> Self {
+        Node::Leaf(p.array(xs))
+    }
+
+    pub fn branch(cs: Vec<P::Pointer<Node<T, P>>>, p: &P) -> Self {
+        let mut s = 0;
+
+        Node::Branch(
+            p.array(
+                cs.iter()
+                    .map(|c| {
+                        s += c.len();
+                        s
+                    })
+                    .collect(),
+            ),
+            p.array(cs),
+        )
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            Node::Leaf(xs) => xs.len(),
+            Node::Branch(_, cs) => cs.len(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn is_balanced(&self, height: usize, root: bool) -> bool {
+        let w = self.width();
+
+        w <= MAX_WIDTH
+            && match self {
+                Node::Leaf(_) => height == 0 && (root || w >= MIN_WIDTH),
+                Node::Branch(ss, cs) => {
+                    height > 0
+                        && w >= if root { 2 } else { MIN_WIDTH }
+                        && **ss == *sizes::<T, P>(cs)
+                        && cs.iter().all(|c| c.is_balanced(height - 1, false))
+                }
+            }
+    }
+}
+
+impl<T: Clone, P: PointerKind> Node<T, P> {
+    pub fn set(&self, i: usize, x: T, p: &P) -> Self {
+        match self {
+            Node::Leaf(xs) => {
+                let mut xs = xs.to_vec();
+                xs[i] = x;
+                Self::leaf(xs, p)
+            }
+            Node::Branch(ss, cs) => {
+                let j = child_index(ss, i);
+                let mut cs = cs.to_vec();
+                cs[j] = p.pointer(cs[j].set(i - offset(ss, j), x, p));
+                Node::Branch(ss.clone(), p.array(cs))
+            }
+        }
+    }
+
+    /// Splits a tree into trees of elements before and after an index.
+    ///
+    /// Only nodes on a path to the index are copied. Partial nodes at edges
+    /// are joined with their siblings, which takes time proportional to
+    /// differences of their heights.
+    pub fn split_at(n: &P::Pointer<Self>, h: usize, i: usize, p: &P) -> (Tree<T, P>, Tree<T, P>) {
+        match &**n {
+            Node::Leaf(xs) => (Self::leaf_tree(&xs[..i], p), Self::leaf_tree(&xs[i..], p)),
+            Node::Branch(ss, cs) => {
+                let j = child_index(ss, i);
+                let (l, r) = Self::split_at(&cs[j], h - 1, i - offset(ss, j), p);
+
+                let l = match l {
+                    Some((l, hl)) if j > 0 => {
+                        let mut ds = cs[..j - 1].to_vec();
+                        ds.extend(Self::join(&cs[j - 1], h - 1, &l, hl, p));
+                        Self::tree(ds, h, p)
+                    }
+                    None if j > 0 => Self::tree(cs[..j].to_vec(), h, p),
+                    l => l,
+                };
+
+                let r = match r {
+                    Some((r, hr)) if j + 1 < cs.len() => {
+                        let mut ds = Self::join(&r, hr, &cs[j + 1], h - 1, p);
+                        ds.extend(cs[j + 2..].iter().cloned());
+                        Self::tree(ds, h, p)
+                    }
+                    None if j + 1 < cs.len() => Self::tree(cs[j + 1..].to_vec(), h, p),
+                    r => r,
+                };
+
+                (l, r)
+            }
+        }
+    }
+
+    fn leaf_tree(xs: &[T], p: &P) -> Tree<T, P> {
+        if xs.is_empty() {
+            None
+        } else {
+            Some((p.pointer(Self::leaf(xs.to_vec(), p)), 0))
+        }
+    }
+
+    // Branches with only one child are replaced with the children.
+    fn tree(cs: Vec<P::Pointer<Self>>, h: usize, p: &P) -> Tree<T, P> {
+        Some(if cs.len() == 1 {
+            (cs[0].clone(), h - 1)
+        } else {
+            (p.pointer(Self::branch(cs, p)), h)
+        })
+    }
+
+    // Joins two trees into one or two nodes at the height of the higher tree.
+    pub fn join(
+        a: &P::Pointer<Self>,
+        ha: usize,
+        b: &P::Pointer<Self>,
+        hb: usize,
+        p: &P,
+    ) -> Vec<P::Pointer<Self>> {
+        match ha.cmp(&hb) {
+            Ordering::Equal => Self::merge(a, b, p),
+            Ordering::Greater => {
+                let mut cs = a.children().to_vec();
+                let c = cs.pop().unwrap();
+                cs.extend(Self::join(&c, ha - 1, b, hb, p));
+                Self::split(cs, Self::branch, p)
+            }
+            Ordering::Less => {
+                let cs = b.children();
+                let mut ds = Self::join(a, ha, &cs[0], hb - 1, p);
+                ds.extend(cs[1..].iter().cloned());
+                Self::split(ds, Self::branch, p)
+            }
+        }
+    }
+
+    fn merge(a: &P::Pointer<Self>, b: &P::Pointer<Self>, p: &P) -> Vec<P::Pointer<Self>> {
+        if a.width() >= MIN_WIDTH && b.width() >= MIN_WIDTH {
+            return vec![a.clone(), b.clone()];
+        }
+
+        match (&**a, &**b) {
+            (Node::Leaf(xs), Node::Leaf(ys)) => {
+                Self::split(xs.iter().chain(ys.iter()).cloned().collect(), Self::leaf, p)
+            }
+            (Node::Branch(_, cs), Node::Branch(_, ds)) => Self::split(
+                cs.iter().chain(ds.iter()).cloned().collect(),
+                Self::branch,
+                p,
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    fn split<U>(mut xs: Vec<U>, f: impl Fn(Vec<U>, &P) -> Self, p: &P) -> Vec<P::Pointer<Self>> {
+        if xs.len() <= MAX_WIDTH {
+            vec![p.pointer(f(xs, p))]
+        } else {
+            let ys = xs.split_off(xs.len() / 2);
+            vec![p.pointer(f(xs, p)), p.pointer(f(ys, p))]
+        }
+    }
+}
+
+impl<T, P: PointerKind> Clone for Node<T, P> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Leaf(xs) => Node::Leaf(xs.clone()),
+            Node::Branch(ss, cs) => Node::Branch(ss.clone(), cs.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+fn sizes<T, P: PointerKind>(cs: &[P::Pointer<Node<T, P>>]) -> Vec<usize> {
+    let mut s = 0;
+
+    cs.iter()
+        .map(|c| {
+            s += c.len();
+            s
+        })
+        .collect()
+}
+
+pub fn child_index(ss: &[usize], i: usize) -> usize {
+    ss.partition_point(|&s| s <= i)
+}
+
+pub fn offset(ss: &[usize], j: usize) -> usize {
+    if j == 0 {
+        0
+    } else {
+        ss[j - 1]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MAX_WIDTH, MIN_WIDTH};
+    use crate::pointer::{ArcK, PointerKind};
+    use std::vec::Vec;
+
+    type Node<T> = super::Node<T, ArcK>;
+
+    fn leaf(n: usize) -> <ArcK as PointerKind>::Pointer<Node<usize>> {
+        ArcK.pointer(Node::leaf((0..n).collect(), &ArcK))
+    }
+
+    #[test]
+    fn branch() {
+        let n = Node::branch(vec![leaf(1), leaf(2), leaf(3)], &ArcK);
+
+        assert_eq!(n.len(), 6);
+        assert_eq!(n.get(0), Some(&0));
+        assert_eq!(n.get(1), Some(&0));
+        assert_eq!(n.get(2), Some(&1));
+        assert_eq!(n.get(5), Some(&2));
+        assert_eq!(n.get(6), None);
+    }
+
+    #[test]
+    fn set() {
+        let n = Node::branch(vec![leaf(2), leaf(2)], &ArcK).set(3, 42, &ArcK);
+
+        assert_eq!(n.get(2), Some(&0));
+        assert_eq!(n.get(3), Some(&42));
+    }
+
+    #[test]
+    fn merge_leaves() {
+        assert_eq!(Node::merge(&leaf(1), &leaf(2), &ArcK).len(), 1);
+        assert_eq!(Node::merge(&leaf(1), &leaf(MAX_WIDTH), &ArcK).len(), 2);
+        assert_eq!(
+            Node::merge(&leaf(MIN_WIDTH), &leaf(MIN_WIDTH), &ArcK).len(),
+            2
+        );
+
+        for n in Node::merge(&leaf(MAX_WIDTH), &leaf(1), &ArcK) {
+            assert!(n.is_balanced(0, false));
+        }
+    }
+
+    #[test]
+    fn join() {
+        let a = ArcK.pointer(Node::branch(
+            (0..MAX_WIDTH).map(|_| leaf(MAX_WIDTH)).collect(),
+            &ArcK,
+        ));
+        let ns = Node::join(&a, 1, &leaf(1), 0, &ArcK);
+
+        assert_eq!(ns.len(), 2);
+        assert_eq!(
+            ns.iter().map(|n| n.len()).sum::<usize>(),
+            MAX_WIDTH * MAX_WIDTH + 1
+        );
+
+        for n in ns {
+            assert!(n.is_balanced(1, false));
+        }
+
+        assert_eq!(Node::join(&leaf(1), 0, &a, 1, &ArcK).len(), 2);
+    }
+
+    #[test]
+    fn join_without_copy() {
+        let ns = Node::join(&leaf(MIN_WIDTH), 0, &leaf(MAX_WIDTH), 0, &ArcK);
+
+        assert_eq!(
+            ns.iter().map(|n| n.len()).collect::<Vec<_>>(),
+            vec![MIN_WIDTH, MAX_WIDTH]
+        );
+    }
+}